@@ -0,0 +1,72 @@
+use chrono::prelude::{Local, NaiveDate};
+use chrono::Duration;
+
+use crate::app::{App, TaskRef};
+
+/// How many days the agenda covers, starting today
+pub const AGENDA_DAYS: i64 = 7;
+
+/// A line of the agenda: a section's header, or a task by its row in the
+/// view.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AgendaLine {
+    /// Tasks whose due date has passed
+    Overdue,
+    Day(NaiveDate),
+    /// Tasks due after the last day
+    Later,
+    Task(usize),
+}
+
+/// The days of the agenda, starting today.
+fn agenda_days() -> impl Iterator<Item = NaiveDate> {
+    let today = Local::now().date_naive();
+    (0..AGENDA_DAYS).map(move |offset| today + Duration::days(offset))
+}
+
+/// Lays out `visible`, which is sorted by due date, under a header for each
+/// day, with overdue tasks above and the rest below. Days with nothing due
+/// keep their header so gaps show, the other sections only show up when
+/// they have tasks.
+pub fn agenda_lines(app: &App, visible: &[TaskRef]) -> Vec<AgendaLine> {
+    let now = Local::now();
+    let due = |task: TaskRef| app.task(task).due_date.with_timezone(&Local);
+    let mut lines = Vec::new();
+    let mut rows = visible.iter().enumerate().peekable();
+    while let Some((row, _)) = rows.next_if(|(_, &task)| due(task) < now) {
+        if lines.is_empty() {
+            lines.push(AgendaLine::Overdue);
+        }
+        lines.push(AgendaLine::Task(row));
+    }
+    for day in agenda_days() {
+        lines.push(AgendaLine::Day(day));
+        while let Some((row, _)) = rows.next_if(|(_, &task)| due(task).date_naive() == day) {
+            lines.push(AgendaLine::Task(row));
+        }
+    }
+    if rows.peek().is_some() {
+        lines.push(AgendaLine::Later);
+        lines.extend(rows.map(|(row, _)| AgendaLine::Task(row)));
+    }
+    lines
+}
+
+/// The header of a section of the agenda. Today and tomorrow go by name,
+/// as the header only has the name column's width.
+pub fn agenda_header(line: AgendaLine) -> String {
+    let today = Local::now().date_naive();
+    match line {
+        AgendaLine::Overdue => "Overdue".to_string(),
+        AgendaLine::Day(day) if day == today => "Today".to_string(),
+        AgendaLine::Day(day) if day == today + Duration::days(1) => "Tomorrow".to_string(),
+        AgendaLine::Day(day) => day.format("%A %Y-%m-%d").to_string(),
+        AgendaLine::Later => "Later".to_string(),
+        AgendaLine::Task(_) => String::new(),
+    }
+}
+
+/// Where the task on `row` ended up among the agenda's lines.
+pub fn line_of(lines: &[AgendaLine], row: usize) -> Option<usize> {
+    lines.iter().position(|&line| line == AgendaLine::Task(row))
+}
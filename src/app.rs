@@ -0,0 +1,2428 @@
+use arboard::Clipboard;
+use chrono::prelude::{DateTime, Local, NaiveDate, Utc};
+use chrono::Months;
+use log::{error, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use tui::layout::Rect;
+use tui::widgets::{ListState, TableState};
+
+use crate::board::{board_columns, Board};
+use crate::bucket::Bucket;
+use crate::command::{parse_command, Command, SortKey};
+use crate::config::{is_valid_date_format, Config, DEFAULT_DATE_FORMAT};
+use crate::core::{add_task, complete_task, find_or_add_list};
+use crate::db::*;
+use crate::duration::parse_duration;
+use crate::export::{export_csv, export_ics, export_yaml, ICS_FILE};
+use crate::form::TaskForm;
+use crate::history::{History, Snapshot};
+use crate::import::{import_file, ImportFormat};
+use crate::keymap::Keymap;
+use crate::notify::check_reminders;
+use crate::recurrence::{spawn_recurring, Recurrence};
+use crate::search::{best_match, fuzzy_match, Finder};
+use crate::state::{read_state, write_state, SortOrder, State};
+use crate::sync::{
+    open_remote, read_sync_state, resolve, sync, write_sync_state, Conflict, Remote, SyncState,
+};
+use crate::taskwarrior::export_taskwarrior;
+use crate::theme::Theme;
+use crate::todoist::{
+    merge, plan, push_and_pull, read_todoist_state, spawn_sync, write_todoist_state, Outcome, Plan,
+};
+use crate::trash::{read_trash, write_trash, Trashed, TRASH_LIMIT};
+
+/// Bounds on the share of the terminal width, in percent, taken by the list panel
+pub const MIN_LIST_WIDTH: u16 = 10;
+pub const MAX_LIST_WIDTH: u16 = 50;
+
+/// Where `i` drops new tasks
+pub const INBOX: &str = "Inbox";
+
+/// Sidebar entry for the completed tasks view
+pub const COMPLETED: &str = "Completed";
+
+/// Sidebar entry for the week ahead, day by day
+pub const AGENDA: &str = "Agenda";
+
+/// How long a flash message stays in the status line
+const FLASH_DURATION: Duration = Duration::from_secs(3);
+
+/// A transient message shown in place of the status line.
+pub struct Flash {
+    pub text: String,
+    pub error: bool,
+    shown_at: Instant,
+}
+
+/// What keys currently go to. Each mode but Normal is left with Esc.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Normal,
+    /// Tasks are marked with space to act on them together
+    Visual,
+    Search,
+    /// Searching every list from a popup
+    Find,
+    Calendar,
+    /// Filling in a new task
+    Form,
+    /// Typing into the status bar prompt
+    Editing,
+    /// Waiting for a y/n answer
+    Confirm,
+    /// Going through the selected task's subtasks
+    Checklist,
+    /// Picking tags to filter by
+    TagPicker,
+    Focus,
+    /// Moving tasks between the columns of the board
+    Board,
+}
+
+/// What a line typed into the status bar is for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    Estimate,
+    Comment,
+    Subtask,
+    Repeat,
+    Reminder,
+    Command,
+    GoToList,
+    Move,
+    TagMarked,
+    NewListAbove,
+    NewListBelow,
+    DependsOn,
+    TagFilter,
+    Capture,
+}
+
+impl PromptKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptKind::Estimate => "Estimate: ",
+            PromptKind::Comment => "Comment: ",
+            PromptKind::Subtask => "Subtask: ",
+            PromptKind::Repeat => "Repeat (daily, weekly, monthly, every 3d, empty for never): ",
+            PromptKind::Reminder => "Remind before (empty for the default): ",
+            PromptKind::Command => ":",
+            PromptKind::GoToList => "Go to list: ",
+            PromptKind::Move => "Move to list: ",
+            PromptKind::TagMarked => "Add tag: ",
+            PromptKind::NewListAbove | PromptKind::NewListBelow => "New list: ",
+            PromptKind::DependsOn => "Blocked by: ",
+            PromptKind::TagFilter => "Filter tags: ",
+            PromptKind::Capture => "Inbox: ",
+        }
+    }
+}
+
+/// A task by its list's index and its index within that list.
+pub type TaskRef = (usize, usize);
+
+/// What the highlighted sidebar entry shows in the task panel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    List(usize),
+    Bucket(Bucket),
+    /// Completed tasks from every list, most recent first
+    Completed,
+    /// Open tasks from every list, overdue ones first, then under a header
+    /// for each day of the next week, then the rest
+    Agenda,
+}
+
+/// Tags a task has to carry to be shown.
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    /// Whether a task needs every tag rather than any of them
+    pub all: bool,
+}
+
+impl TagFilter {
+    pub fn matches(&self, task: &Task) -> bool {
+        let has = |tag: &String| task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        if self.all {
+            self.tags.iter().all(has)
+        } else {
+            self.tags.iter().any(has)
+        }
+    }
+}
+
+/// The popup `t` opens to pick tags to filter by from every tag in use.
+pub struct TagPicker {
+    pub tags: Vec<String>,
+    /// Index of the highlighted tag
+    pub selected: usize,
+    /// Tags picked so far, in the order they were picked
+    pub chosen: Vec<String>,
+}
+
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+/// Something held back until the user answers a yes/no question.
+enum Pending {
+    InsertList(String, usize),
+    Capture(String),
+    DeleteTask(TaskRef),
+    DeleteMarked,
+    DeleteList(usize),
+    /// The first of the sync's conflicts, yes keeps the newer copy
+    SyncConflict,
+}
+
+/// A yes/no question shown in the status line.
+pub struct Confirm {
+    pub question: String,
+    action: Pending,
+}
+
+impl Confirm {
+    /// Whether no is an answer that acts, so it has to be typed rather than
+    /// given by any other key.
+    pub fn acts_on_no(&self) -> bool {
+        matches!(self.action, Pending::SyncConflict)
+    }
+}
+
+/// A Todoist sync running in the background.
+struct TodoistSync {
+    receiver: Receiver<Result<Outcome, Error>>,
+    started: DateTime<Utc>,
+}
+
+/// A sync waiting for the user to settle the tasks changed on both sides.
+struct Syncing {
+    remote: Box<dyn Remote>,
+    state: SyncState,
+    conflicts: Vec<Conflict>,
+}
+
+/// Everything the main loop needs between frames.
+pub struct App {
+    pub config: Config,
+    pub state: State,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub db_path: PathBuf,
+    pub tasklists: Vec<TaskList>,
+    pub list_state: ListState,
+    pub task_state: TableState,
+    /// Row last selected in each list, by list id, for coming back to it
+    list_rows: HashMap<usize, usize>,
+    pub search: Option<String>,
+    /// The last search submitted with Enter, for stepping through its matches
+    pub last_search: Option<String>,
+    /// The search across every list, `None` when it's closed
+    pub finder: Option<Finder>,
+    pub prompt: Option<Prompt>,
+    /// The new task form, while it's open
+    pub form: Option<TaskForm>,
+    pub confirm: Option<Confirm>,
+    syncing: Option<Syncing>,
+    todoist_sync: Option<TodoistSync>,
+    /// Tasks marked in visual mode, `None` outside of it
+    pub marked: Option<HashSet<TaskRef>>,
+    /// Day highlighted in the calendar, `None` when it's closed
+    pub calendar: Option<NaiveDate>,
+    /// Sidebar entry the agenda was opened from, which closing it goes
+    /// back to
+    before_agenda: Option<usize>,
+    /// Only show tasks due on this day
+    pub day_filter: Option<NaiveDate>,
+    pub tag_filter: Option<TagFilter>,
+    pub tag_picker: Option<TagPicker>,
+    /// Only show tasks with this status
+    pub status_filter: Option<Status>,
+    /// Whether only the next task to work on is shown
+    pub focus: bool,
+    /// The board of tasks by status, `None` when it's closed
+    pub board: Option<Board>,
+    /// Row selected in the selected task's subtasks, `None` when they're
+    /// not open
+    pub checklist: Option<usize>,
+    pub flash: Option<Flash>,
+    pub list_width: u16,
+    pub collapsed: bool,
+    /// Whether panels are drawn without borders and with fewer columns
+    pub dense: bool,
+    /// Whether the column with the time from start to due date is shown
+    pub show_span: bool,
+    /// Whether everything about the selected task is shown beside the tasks
+    pub show_details: bool,
+    /// Whether open tasks' due dates are shown as the time left
+    pub countdown: bool,
+    /// Whether a line of key hints for the focused panel is shown
+    pub show_hints: bool,
+    /// Whether the sidebar puts the lists with the most pressing tasks first
+    pub urgency_sort: bool,
+    /// Whether lists leave out their completed tasks
+    pub hide_completed: bool,
+    /// How many task columns after Name are scrolled out of view
+    pub column_offset: usize,
+    /// Deleted tasks, most recent last
+    pub trash: Vec<Trashed>,
+    /// Opened on the first yank and kept so the copied text stays available
+    clipboard: Option<Clipboard>,
+    // Where the panels were last drawn and how far they were scrolled, for mouse clicks
+    pub list_area: Rect,
+    pub task_area: Rect,
+    pub list_offset: usize,
+    pub task_offset: usize,
+    /// Whether there are changes that haven't been written to the db
+    pub dirty: bool,
+    /// Ids of the lists changed since the last save
+    changed_lists: HashSet<usize>,
+    /// Whether the changes reach past single lists, like adding or reordering
+    /// them, so the whole db has to be written
+    all_changed: bool,
+    /// Counts changes to the tasks, so a key press can tell it made one
+    pub revision: usize,
+    pub history: History,
+    /// The tasks and trash as of the last change with the revision they were
+    /// taken at, so key presses that change nothing don't copy them
+    undo_point: Option<(usize, Snapshot)>,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(config: Config, db_path: PathBuf) -> Result<App, Error> {
+        let tasklists = read_db(&db_path)?;
+        let state = read_state(&db_path);
+        let trash = read_trash(&db_path);
+        let mut app = App::with_tasks(config, state, db_path, tasklists);
+        app.trash = trash;
+        Ok(app)
+    }
+
+    /// Builds the app around lists that were already loaded.
+    pub fn with_tasks(
+        mut config: Config,
+        state: State,
+        db_path: PathBuf,
+        mut tasklists: Vec<TaskList>,
+    ) -> App {
+        for list in &mut tasklists {
+            if let Some(order) = state.sort.get(&list.id) {
+                order.apply(&mut list.tasks);
+            }
+        }
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        let (keymap, key_errors) = Keymap::new(&config.keys);
+        let (theme, theme_errors) = Theme::new(&config.theme, &config.colors);
+        let bad_date_format = !is_valid_date_format(&config.date_format);
+        if bad_date_format {
+            config.date_format = DEFAULT_DATE_FORMAT.to_string();
+        }
+        let mut app = App {
+            list_width: config.list_width.clamp(MIN_LIST_WIDTH, MAX_LIST_WIDTH),
+            countdown: config.countdown,
+            config,
+            state,
+            keymap,
+            theme,
+            db_path,
+            tasklists,
+            list_state,
+            task_state: TableState::default(),
+            list_rows: HashMap::new(),
+            search: None,
+            last_search: None,
+            finder: None,
+            prompt: None,
+            form: None,
+            confirm: None,
+            syncing: None,
+            todoist_sync: None,
+            marked: None,
+            calendar: None,
+            before_agenda: None,
+            day_filter: None,
+            tag_filter: None,
+            tag_picker: None,
+            status_filter: None,
+            focus: false,
+            board: None,
+            checklist: None,
+            flash: None,
+            collapsed: false,
+            dense: false,
+            show_span: false,
+            show_details: false,
+            show_hints: false,
+            urgency_sort: false,
+            hide_completed: false,
+            column_offset: 0,
+            trash: Vec::new(),
+            clipboard: None,
+            list_area: Rect::default(),
+            task_area: Rect::default(),
+            list_offset: 0,
+            task_offset: 0,
+            dirty: false,
+            changed_lists: HashSet::new(),
+            all_changed: false,
+            revision: 0,
+            history: History::default(),
+            undo_point: None,
+            quit: false,
+        };
+        if bad_date_format {
+            app.flash_error("Invalid date_format in config, using the default".to_string());
+        }
+        for err in key_errors.into_iter().chain(theme_errors) {
+            warn!("{}", err);
+            app.flash_error(err);
+        }
+        for list in 0..app.tasklists.len() {
+            if spawn_recurring(&mut app.tasklists[list], chrono::Utc::now()) {
+                let result = app.mark_list_dirty(list);
+                app.report(result);
+            }
+        }
+        let mut backwards = 0;
+        for list in &app.tasklists {
+            for task in list.tasks.iter().filter(|task| task.due_before_start()) {
+                warn!("{} / {} is due before it starts", list.name, task.name);
+                backwards += 1;
+            }
+        }
+        if backwards > 0 {
+            app.flash_error(format!(
+                "{} task{} due before {} start",
+                backwards,
+                if backwards == 1 { " is" } else { "s are" },
+                if backwards == 1 { "it" } else { "they" }
+            ));
+        }
+        // The lists may have changed since the selection was saved
+        let entry = app.state.selected_entry.min(app.sidebar_len() - 1);
+        app.list_state.select(Some(entry));
+        app.task_state.select(app.state.selected_row);
+        app.clamp_selection();
+        app
+    }
+
+    /// Index of the highlighted sidebar entry: the lists, then the buckets.
+    pub fn selected_entry(&self) -> usize {
+        self.list_state
+            .selected()
+            .expect("There must be a selected list")
+    }
+
+    /// Number of sidebar entries.
+    pub fn sidebar_len(&self) -> usize {
+        self.tasklists.len() + Bucket::ALL.len() + 2
+    }
+
+    pub fn view(&self) -> View {
+        let entry = self.selected_entry();
+        match entry.checked_sub(self.tasklists.len()) {
+            Some(bucket) if bucket < Bucket::ALL.len() => View::Bucket(Bucket::ALL[bucket]),
+            Some(bucket) if bucket == Bucket::ALL.len() => View::Completed,
+            Some(_) => View::Agenda,
+            None => View::List(self.sidebar_lists()[entry]),
+        }
+    }
+
+    /// Indices of the lists in sidebar order: pinned ones first, then by
+    /// urgency if that's on, otherwise as they're stored.
+    pub fn sidebar_lists(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.tasklists.len()).collect();
+        if self.urgency_sort {
+            let now = Local::now();
+            order.sort_by_key(|&list| {
+                let list = &self.tasklists[list];
+                (!list.pinned, std::cmp::Reverse(urgency(list, now)))
+            });
+        } else {
+            order.sort_by_key(|&list| !self.tasklists[list].pinned);
+        }
+        order
+    }
+
+    /// Turns sorting the sidebar by urgency on or off, staying on the same
+    /// entry.
+    pub fn toggle_urgency_sort(&mut self) {
+        let list = self.selected_list();
+        self.urgency_sort = !self.urgency_sort;
+        if let Some(list) = list {
+            self.list_state.select(Some(self.entry_of(list)));
+        }
+    }
+
+    /// Sidebar entry of a list.
+    fn entry_of(&self, list: usize) -> usize {
+        self.sidebar_lists()
+            .iter()
+            .position(|&l| l == list)
+            .expect("every list is in the sidebar")
+    }
+
+    /// The selected list, or `None` while a bucket is selected.
+    pub fn selected_list(&self) -> Option<usize> {
+        match self.view() {
+            View::List(list) => Some(list),
+            View::Bucket(_) | View::Completed | View::Agenda => None,
+        }
+    }
+
+    pub fn view_name(&self) -> &str {
+        match self.view() {
+            View::List(list) => &self.tasklists[list].name,
+            View::Bucket(bucket) => bucket.name(),
+            View::Completed => COMPLETED,
+            View::Agenda => AGENDA,
+        }
+    }
+
+    pub fn task(&self, (list, task): TaskRef) -> &Task {
+        &self.tasklists[list].tasks[task]
+    }
+
+    /// The task to edit, which counts as touching it for the stale highlight.
+    pub fn task_mut(&mut self, (list, task): TaskRef) -> &mut Task {
+        let task = &mut self.tasklists[list].tasks[task];
+        task.updated_at = Some(chrono::Utc::now());
+        task
+    }
+
+    /// Every task in every list.
+    fn all_tasks(&self) -> impl Iterator<Item = TaskRef> + '_ {
+        self.tasklists
+            .iter()
+            .enumerate()
+            .flat_map(|(l, list)| (0..list.tasks.len()).map(move |t| (l, t)))
+    }
+
+    /// Tasks in the current view that pass the active filters, in display
+    /// order. Table rows index into this, not into the tasks.
+    pub fn visible_tasks(&self) -> Vec<TaskRef> {
+        let mut visible: Vec<TaskRef> = match self.view() {
+            View::List(list) => (0..self.tasklists[list].tasks.len())
+                .map(|task| (list, task))
+                .filter(|&r| !self.hide_completed || !self.task(r).is_completed())
+                .collect(),
+            View::Bucket(bucket) => {
+                let now = Local::now();
+                let mut visible: Vec<TaskRef> = self
+                    .all_tasks()
+                    .filter(|&r| {
+                        let task = self.task(r);
+                        !task.is_completed() && Bucket::of(task.due_date, now) == bucket
+                    })
+                    .collect();
+                visible.sort_by_key(|&r| self.task(r).due_date);
+                visible
+            }
+            View::Completed => {
+                let mut visible: Vec<TaskRef> = self
+                    .all_tasks()
+                    .filter(|&r| self.task(r).is_completed())
+                    .collect();
+                visible.sort_by_key(|&r| std::cmp::Reverse(self.task(r).completed_at));
+                visible
+            }
+            View::Agenda => {
+                let mut visible: Vec<TaskRef> = self
+                    .all_tasks()
+                    .filter(|&r| !self.task(r).is_completed())
+                    .collect();
+                visible.sort_by_key(|&r| self.task(r).due_date);
+                visible
+            }
+        };
+        if let Some(day) = self.day_filter {
+            visible.retain(|&r| self.task(r).due_date.with_timezone(&Local).date_naive() == day);
+        }
+        if let Some(filter) = &self.tag_filter {
+            visible.retain(|&r| filter.matches(self.task(r)));
+        }
+        if let Some(status) = self.status_filter {
+            visible.retain(|&r| self.task(r).status == status);
+        }
+        visible
+    }
+
+    /// Moves focus back to the list panel, remembering the selected row.
+    pub fn leave_tasks(&mut self) {
+        if let (Some(list), Some(row)) = (self.selected_list(), self.task_state.selected()) {
+            self.list_rows.insert(self.tasklists[list].id, row);
+        }
+        self.task_state.select(None);
+        self.marked = None;
+    }
+
+    /// Moves focus to the task panel, on the row last selected in this list.
+    pub fn enter_tasks(&mut self) {
+        let len = self.visible_tasks().len();
+        if len == 0 {
+            return;
+        }
+        let row = self
+            .selected_list()
+            .and_then(|list| self.list_rows.get(&self.tasklists[list].id))
+            .map_or(0, |&row| row.min(len - 1));
+        self.task_state.select(Some(row));
+    }
+
+    /// The task on the highlighted row.
+    pub fn selected_task(&self) -> Option<TaskRef> {
+        let row = self.task_state.selected()?;
+        self.visible_tasks().get(row).copied()
+    }
+
+    /// Keeps the highlighted row on a visible task after the rows changed.
+    pub fn clamp_selection(&mut self) {
+        if let Some(row) = self.task_state.selected() {
+            match self.visible_tasks().len() {
+                0 => self.task_state.select(None),
+                len => self.task_state.select(Some(row.min(len - 1))),
+            }
+        }
+    }
+
+    pub fn flash_info(&mut self, text: String) {
+        self.flash = Some(Flash {
+            text,
+            error: false,
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub fn flash_error(&mut self, text: String) {
+        self.flash = Some(Flash {
+            text,
+            error: true,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Flashes the error of a failed operation instead of bailing out.
+    pub fn report(&mut self, result: Result<(), Error>) {
+        if let Err(err) = result {
+            error!("{}", err);
+            self.flash_error(err.to_string());
+        }
+    }
+
+    /// Clears the flash message once it has been shown long enough.
+    pub fn expire_flash(&mut self) {
+        if let Some(flash) = &self.flash {
+            if flash.shown_at.elapsed() >= FLASH_DURATION {
+                self.flash = None;
+            }
+        }
+    }
+
+    /// Records an in-memory change; it's written out by `save`.
+    fn mark_dirty(&mut self) -> Result<(), Error> {
+        self.dirty = true;
+        self.all_changed = true;
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Records a change to one list, so saving only has to write that list.
+    /// Lists set to stay sorted are sorted again here.
+    fn mark_list_dirty(&mut self, list: usize) -> Result<(), Error> {
+        self.resort(list);
+        self.dirty = true;
+        self.changed_lists.insert(self.tasklists[list].id);
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// The tasks and trash as they are now, to come back to with undo.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tasklists: self.tasklists.clone(),
+            trash: self.trash.clone(),
+        }
+    }
+
+    /// Makes sure there's a snapshot to record if the next key press changes
+    /// the tasks. They're only copied again once they have changed.
+    pub fn prepare_undo(&mut self) {
+        if self.undo_point.as_ref().map(|(revision, _)| *revision) != Some(self.revision) {
+            self.undo_point = Some((self.revision, self.snapshot()));
+        }
+    }
+
+    /// Records the snapshot from before a key press that changed the tasks,
+    /// so it can be undone.
+    pub fn record_undo(&mut self) {
+        if let Some((_, before)) = self.undo_point.take() {
+            self.history.record(before);
+        }
+    }
+
+    /// Undoes the last change to the tasks, or with `redo` puts back the
+    /// last one undone.
+    pub fn undo(&mut self, redo: bool) {
+        let current = self.snapshot();
+        let restored = if redo {
+            self.history.redo(current)
+        } else {
+            self.history.undo(current)
+        };
+        let restored = match restored {
+            Some(restored) => restored,
+            None => {
+                let what = if redo { "redo" } else { "undo" };
+                self.flash_error(format!("Nothing to {}", what));
+                return;
+            }
+        };
+        self.tasklists = restored.tasklists;
+        self.trash = restored.trash;
+        self.undo_point = None;
+        // Lists may have come or gone, so everything is written
+        self.dirty = true;
+        self.all_changed = true;
+        let entry = self.selected_entry().min(self.sidebar_len() - 1);
+        self.list_state.select(Some(entry));
+        self.clamp_selection();
+    }
+
+    /// Sorts the list again if it's set to stay sorted, keeping the same task
+    /// selected.
+    fn resort(&mut self, list: usize) {
+        let order = match self.state.sort.get(&self.tasklists[list].id) {
+            Some(&order) if order.auto => order,
+            _ => return,
+        };
+        let selected = self
+            .selected_task()
+            .filter(|&(l, _)| l == list)
+            .map(|task| self.task(task).id);
+        order.apply(&mut self.tasklists[list].tasks);
+        if let Some(id) = selected {
+            let task = self.tasklists[list].tasks.iter().position(|t| t.id == id);
+            let row =
+                task.and_then(|task| self.visible_tasks().iter().position(|&r| r == (list, task)));
+            self.task_state.select(row);
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        if self.dirty {
+            if self.all_changed {
+                write_db(&self.db_path, &self.tasklists)?;
+            } else {
+                for &id in &self.changed_lists {
+                    write_list(&self.db_path, &self.tasklists, id)?;
+                }
+            }
+            write_state(&self.db_path, &self.state)?;
+            write_trash(&self.db_path, &self.trash)?;
+            self.changed_lists.clear();
+            self.all_changed = false;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Saves any changes if `autosave` is on.
+    pub fn autosave(&mut self) -> Result<(), Error> {
+        if self.config.autosave && !self.quit {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Quits, saving first unless the changes are being discarded.
+    pub fn quit(&mut self, save: bool) -> Result<(), Error> {
+        if save {
+            self.save()?;
+        }
+        self.state.selected_entry = self.selected_entry();
+        self.state.selected_row = self.task_state.selected();
+        if !self.config.keep_trash {
+            self.trash.clear();
+            write_trash(&self.db_path, &self.trash)?;
+        }
+        self.quit = true;
+        write_state(&self.db_path, &self.state)
+    }
+
+    /// Starts the selected task's timer, stopping any other, or stops it.
+    pub fn toggle_timer(&mut self, task: TaskRef) -> Result<(), Error> {
+        if self.task(task).running_since.is_some() {
+            self.task_mut(task).stop_timer();
+        } else {
+            // Only one task is timed at a time
+            for task in self.tasklists.iter_mut().flat_map(|l| l.tasks.iter_mut()) {
+                task.stop_timer();
+            }
+            self.task_mut(task).start_timer();
+        }
+        self.mark_dirty()
+    }
+
+    /// The task to work on next: the soonest due one that isn't done or
+    /// waiting on another task.
+    pub fn next_action(&self) -> Option<TaskRef> {
+        self.all_tasks()
+            .filter(|&r| {
+                let task = self.task(r);
+                !task.is_completed() && !self.tasklists[r.0].is_blocked(task)
+            })
+            .min_by_key(|&r| self.task(r).due_date)
+    }
+
+    /// Checks the task off, or puts it back on the to do list. Tasks waiting
+    /// on unfinished dependencies can't be checked off.
+    pub fn toggle_completed(&mut self, task: TaskRef) -> Result<(), Error> {
+        let (list, _) = task;
+        if self.task(task).is_completed() {
+            let task = self.task_mut(task);
+            task.completed_at = None;
+            task.status = Status::Todo;
+        } else if !complete_task(&mut self.tasklists[list], task.1, chrono::Utc::now()) {
+            let message = format!("{} is blocked by unfinished tasks", self.task(task).name);
+            self.flash_error(message);
+            return Ok(());
+        }
+        self.clamp_selection();
+        self.mark_list_dirty(list)
+    }
+
+    /// Gives the task `status`, checking it off when that's Done and back on
+    /// when it was.
+    pub fn set_status(&mut self, task: TaskRef, status: Status) -> Result<(), Error> {
+        if self.task(task).is_completed() != (status == Status::Done) {
+            self.toggle_completed(task)?;
+            if self.task(task).is_completed() != (status == Status::Done) {
+                // Blocked, which has already been said
+                return Ok(());
+            }
+        }
+        if self.task(task).status != status {
+            self.task_mut(task).status = status;
+        }
+        self.clamp_selection();
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Steps the task on to its next status, Done and back to Todo going
+    /// through the same checks as completing it.
+    pub fn cycle_status(&mut self, task: TaskRef) -> Result<(), Error> {
+        let next = match self.task(task).status {
+            Status::Todo => Status::InProgress,
+            Status::InProgress => Status::Waiting,
+            Status::Waiting | Status::Done => return self.toggle_completed(task),
+        };
+        self.task_mut(task).status = next;
+        self.clamp_selection();
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Raises the task's priority a step, going from Urgent back to Low.
+    pub fn cycle_priority(&mut self, task: TaskRef) -> Result<(), Error> {
+        let next = self.task(task).priority.next();
+        self.task_mut(task).priority = next;
+        self.clamp_selection();
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Sends the reminders that are due and records that they were sent.
+    pub fn remind(&mut self) -> Result<(), Error> {
+        let lead =
+            parse_duration(&self.config.reminder_lead).unwrap_or_else(chrono::Duration::zero);
+        for list in check_reminders(&mut self.tasklists, lead) {
+            self.mark_list_dirty(list)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_reminder_lead(&mut self, task: TaskRef, lead: Option<i64>) -> Result<(), Error> {
+        self.task_mut(task).reminder_lead = lead;
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Adds a timestamped note to the task's comments.
+    pub fn add_comment(&mut self, task: TaskRef, text: &str) -> Result<(), Error> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.task_mut(task).comments.push(Comment {
+            text: text.to_string(),
+            at: chrono::Utc::now(),
+        });
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Adds a subtask to the end of the task's checklist and selects it.
+    pub fn add_subtask(&mut self, task: TaskRef, name: &str) -> Result<(), Error> {
+        if name.is_empty() {
+            return Ok(());
+        }
+        let subtasks = &mut self.task_mut(task).subtasks;
+        subtasks.push(Subtask {
+            name: name.to_string(),
+            done: false,
+        });
+        self.checklist = Some(subtasks.len() - 1);
+        self.mark_list_dirty(task.0)
+    }
+
+    pub fn toggle_subtask(&mut self, task: TaskRef, index: usize) -> Result<(), Error> {
+        match self.task_mut(task).subtasks.get_mut(index) {
+            Some(subtask) => subtask.done = !subtask.done,
+            None => return Ok(()),
+        }
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Removes a subtask, keeping the checklist's selection in range.
+    pub fn delete_subtask(&mut self, task: TaskRef, index: usize) -> Result<(), Error> {
+        let subtasks = &mut self.task_mut(task).subtasks;
+        if index >= subtasks.len() {
+            return Ok(());
+        }
+        subtasks.remove(index);
+        let len = subtasks.len();
+        self.checklist = self.checklist.map(|row| row.min(len.saturating_sub(1)));
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Sets how often the task repeats from a rule like "weekly", or stops
+    /// it repeating if `rule` is empty.
+    pub fn set_recurrence(&mut self, task: TaskRef, rule: &str) -> Result<(), Error> {
+        let recurrence = if rule.is_empty() {
+            None
+        } else {
+            match Recurrence::parse(rule) {
+                Some(recurrence) => Some(recurrence),
+                None => {
+                    self.flash_error(format!("Can't repeat {}", rule));
+                    return Ok(());
+                }
+            }
+        };
+        self.task_mut(task).recurrence = recurrence;
+        self.mark_list_dirty(task.0)
+    }
+
+    pub fn set_estimate(&mut self, task: TaskRef, estimate: Option<i64>) -> Result<(), Error> {
+        self.task_mut(task).estimate = estimate;
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Pushes the task's due date back by `by`, unless that would put it
+    /// before the start date.
+    pub fn snooze(&mut self, task: TaskRef, by: chrono::Duration) -> Result<(), Error> {
+        let due = self.task(task).due_date + by;
+        if due < self.task(task).start_date {
+            self.flash_error("The due date can't be before the start date".to_string());
+            return Ok(());
+        }
+        self.task_mut(task).due_date = due;
+        self.clamp_selection();
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Makes `task` wait on another task of the same list.
+    pub fn add_dependency(&mut self, task: TaskRef, dependency: usize) -> Result<(), Error> {
+        let (list, _) = task;
+        let id = self.task(task).id;
+        let dependency_id = self.tasklists[list].tasks[dependency].id;
+        if self.tasklists[list].depends_transitively(dependency_id, id) {
+            self.flash_error("That would make a dependency cycle".to_string());
+            return Ok(());
+        }
+        let depends_on = &mut self.task_mut(task).depends_on;
+        if !depends_on.contains(&dependency_id) {
+            depends_on.push(dependency_id);
+        }
+        let message = format!(
+            "{} is blocked by {}",
+            self.task(task).name,
+            self.tasklists[list].tasks[dependency].name
+        );
+        self.flash_info(message);
+        self.mark_list_dirty(list)
+    }
+
+    pub fn clear_dependencies(&mut self, task: TaskRef) -> Result<(), Error> {
+        self.task_mut(task).depends_on.clear();
+        self.mark_list_dirty(task.0)
+    }
+
+    /// Moves the task to the start or end of its list, keeping it selected.
+    /// The list's saved sort order is dropped since it's ordered by hand now.
+    pub fn move_task_to_end(&mut self, (list, task): TaskRef, top: bool) -> Result<(), Error> {
+        let tasks = &mut self.tasklists[list].tasks;
+        let moved = tasks.remove(task);
+        let index = if top { 0 } else { tasks.len() };
+        tasks.insert(index, moved);
+        let id = self.tasklists[list].id;
+        self.state.sort.remove(&id);
+        let row = self
+            .visible_tasks()
+            .iter()
+            .position(|&r| r == (list, index));
+        self.task_state.select(row);
+        self.mark_list_dirty(list)
+    }
+
+    /// Swaps the task on `row` with the one shown above or below it, keeping
+    /// it selected. Like moving it to either end, this drops the list's sort.
+    pub fn move_task(&mut self, row: usize, up: bool) -> Result<(), Error> {
+        if self.selected_list().is_none() {
+            self.flash_error("Tasks can only be reordered in their own list".to_string());
+            return Ok(());
+        }
+        let visible = self.visible_tasks();
+        let target = if up {
+            row.checked_sub(1)
+        } else {
+            Some(row + 1)
+        };
+        let (list, task, other) = match target.and_then(|target| visible.get(target)) {
+            Some(&(list, other)) => (list, visible[row].1, other),
+            None => return Ok(()),
+        };
+        self.tasklists[list].tasks.swap(task, other);
+        let id = self.tasklists[list].id;
+        self.state.sort.remove(&id);
+        self.task_state.select(target);
+        self.mark_list_dirty(list)
+    }
+
+    /// Copies the task's name, tags and dates into a new task right below it
+    /// and selects the copy. Progress like the status, time and comments
+    /// starts over.
+    pub fn duplicate_task(&mut self, (list, task): TaskRef) -> Result<(), Error> {
+        let tasks = &mut self.tasklists[list].tasks;
+        let original = &tasks[task];
+        let id = tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+        let copy = Task {
+            tags: original.tags.clone(),
+            start_date: original.start_date,
+            ..Task::new(id, format!("{} (copy)", original.name), original.due_date)
+        };
+        tasks.insert(task + 1, copy);
+        let row = self
+            .visible_tasks()
+            .iter()
+            .position(|&r| r == (list, task + 1));
+        self.task_state.select(row);
+        self.mark_list_dirty(list)
+    }
+
+    /// Copies the task's name to the system clipboard.
+    pub fn yank(&mut self, task: TaskRef) {
+        let name = self.task(task).name.clone();
+        let copied = match &mut self.clipboard {
+            Some(clipboard) => clipboard.set_text(name.clone()),
+            None => Clipboard::new().and_then(|mut clipboard| {
+                clipboard.set_text(name.clone())?;
+                self.clipboard = Some(clipboard);
+                Ok(())
+            }),
+        };
+        match copied {
+            Ok(()) => self.flash_info(format!("Copied {}", name)),
+            Err(err) => {
+                error!("couldn't copy to the clipboard: {}", err);
+                self.flash_error(format!("Couldn't copy to the clipboard: {}", err));
+            }
+        }
+    }
+
+    pub fn resize_list_panel(&mut self, grow: bool) {
+        self.list_width = if grow {
+            (self.list_width + 5).min(MAX_LIST_WIDTH)
+        } else {
+            self.list_width.saturating_sub(5).max(MIN_LIST_WIDTH)
+        };
+    }
+
+    pub fn toggle_collapsed(&mut self) {
+        self.collapsed = !self.collapsed;
+        // The list panel can't be focused while it's hidden
+        let has_tasks = !self.visible_tasks().is_empty();
+        if self.collapsed && self.task_state.selected().is_none() && has_tasks {
+            self.task_state.select(Some(0));
+        }
+    }
+
+    pub fn new_list(&mut self, name: String) -> Result<(), Error> {
+        self.insert_list(name, self.tasklists.len())
+    }
+
+    /// Adds an empty list at `index` in the sidebar and selects it, asking
+    /// first if there's already a list by that name.
+    pub fn insert_list(&mut self, name: String, index: usize) -> Result<(), Error> {
+        if self
+            .tasklists
+            .iter()
+            .any(|l| l.name.eq_ignore_ascii_case(&name))
+        {
+            self.confirm = Some(Confirm {
+                question: format!("A list named {} already exists, add anyway? (y/n)", name),
+                action: Pending::InsertList(name, index),
+            });
+            return Ok(());
+        }
+        self.add_list(name, index)
+    }
+
+    fn add_list(&mut self, name: String, index: usize) -> Result<(), Error> {
+        let id = self.tasklists.iter().map(|l| l.id + 1).max().unwrap_or(0);
+        self.tasklists.insert(index, TaskList::new(id, name));
+        self.list_state.select(Some(self.entry_of(index)));
+        self.task_state.select(None);
+        self.mark_dirty()
+    }
+
+    /// Adds a task to the inbox list, creating it if needed, without moving
+    /// the selection. It's due one snooze from now. Asks first if the inbox
+    /// already has a task by that name.
+    pub fn capture(&mut self, name: String) -> Result<(), Error> {
+        let exists = self
+            .tasklists
+            .iter()
+            .filter(|list| list.name.eq_ignore_ascii_case(INBOX))
+            .flat_map(|list| &list.tasks)
+            .any(|task| task.name.eq_ignore_ascii_case(&name));
+        if exists {
+            self.confirm = Some(Confirm {
+                question: format!("A task named {} already exists, add anyway? (y/n)", name),
+                action: Pending::Capture(name),
+            });
+            return Ok(());
+        }
+        self.add_to_inbox(name)
+    }
+
+    fn add_to_inbox(&mut self, name: String) -> Result<(), Error> {
+        let in_bucket = self.selected_list().is_none();
+        let (inbox, added) = find_or_add_list(&mut self.tasklists, INBOX);
+        if added {
+            self.keep_bucket_selected(in_bucket, 1);
+            self.mark_dirty()?;
+        }
+        let due = parse_duration(&self.config.snooze).unwrap_or_else(|| chrono::Duration::days(1));
+        add_task(
+            &mut self.tasklists[inbox],
+            Task::new(0, name, chrono::Utc::now() + due),
+        );
+        self.flash_info(format!("Added to {}", INBOX));
+        self.mark_list_dirty(inbox)
+    }
+
+    /// Gives the selected list the next sidebar color.
+    pub fn cycle_list_color(&mut self) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => return Ok(()),
+        };
+        let color = ListColor::cycle(self.tasklists[list].color);
+        self.tasklists[list].color = color;
+        self.flash_info(format!("Color: {}", color.map_or("none", ListColor::name)));
+        self.mark_list_dirty(list)
+    }
+
+    /// Adds the tasks in the file at `path`, each to the list named after its
+    /// project.
+    pub fn import_tasks(&mut self, format: ImportFormat, path: &Path) -> Result<(), Error> {
+        let in_bucket = self.selected_list().is_none();
+        let (tasks, lists) = import_file(&mut self.tasklists, format, path)?;
+        self.keep_bucket_selected(in_bucket, lists);
+        for list in 0..self.tasklists.len() {
+            self.resort(list);
+        }
+        self.mark_dirty()?;
+        self.flash_info(format!("Imported {} tasks from {}", tasks, path.display()));
+        Ok(())
+    }
+
+    /// Buckets sit below the lists, so one that was selected before `added`
+    /// lists came in is kept selected.
+    fn keep_bucket_selected(&mut self, in_bucket: bool, added: usize) {
+        if in_bucket {
+            self.list_state.select(Some(self.selected_entry() + added));
+        }
+    }
+
+    /// Syncs every list with the CalDAV server and the Todoist account set up
+    /// in the config.
+    pub fn sync(&mut self) -> Result<(), Error> {
+        if self.config.caldav.is_none() && self.config.todoist.is_none() {
+            self.flash_error("Set up [caldav] or [todoist] in the config to sync".to_string());
+            return Ok(());
+        }
+        if let Some(config) = self.config.todoist.clone() {
+            self.sync_todoist_with(move |plan| push_and_pull(&config, plan));
+        }
+        match &self.config.caldav {
+            Some(config) => {
+                let remote = open_remote(config)?;
+                self.sync_with(remote)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Starts sending the local changes to Todoist with `sync`, on a thread
+    /// of its own. What comes back is merged in by `finish_todoist_sync`.
+    pub fn sync_todoist_with<F>(&mut self, sync: F)
+    where
+        F: FnOnce(Plan) -> Result<Outcome, Error> + Send + 'static,
+    {
+        if self.todoist_sync.is_some() {
+            self.flash_error("Already syncing with Todoist".to_string());
+            return;
+        }
+        let started = Utc::now();
+        let plan = plan(&self.tasklists, &read_todoist_state(&self.db_path));
+        self.todoist_sync = Some(TodoistSync {
+            receiver: spawn_sync(sync, plan),
+            started,
+        });
+        self.flash_info("Syncing with Todoist".to_string());
+    }
+
+    /// Merges in and saves what Todoist sent back, once it has. Called on
+    /// every tick.
+    pub fn finish_todoist_sync(&mut self) -> Result<(), Error> {
+        let received = match &self.todoist_sync {
+            Some(sync) => sync.receiver.try_recv(),
+            None => return Ok(()),
+        };
+        let outcome = match received {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return Ok(()),
+            Err(TryRecvError::Disconnected) => {
+                self.todoist_sync = None;
+                self.flash_error("Syncing with Todoist stopped".to_string());
+                return Ok(());
+            }
+        };
+        let started = self.todoist_sync.take().expect("sync is running").started;
+        let outcome = outcome?;
+        let in_bucket = self.selected_list().is_none();
+        let before = self.tasklists.len();
+        let mut state = read_todoist_state(&self.db_path);
+        let (pulled, pushed) = merge(&mut self.tasklists, &mut state, outcome, started);
+        write_todoist_state(&self.db_path, &state)?;
+        self.keep_bucket_selected(in_bucket, self.tasklists.len() - before);
+        for list in 0..self.tasklists.len() {
+            self.resort(list);
+        }
+        self.clamp_selection();
+        self.mark_dirty()?;
+        self.save()?;
+        self.flash_info(format!(
+            "Synced with Todoist, pulled {} and pushed {} tasks",
+            pulled, pushed
+        ));
+        Ok(())
+    }
+
+    /// Syncs with `remote` and saves, then asks about each task that changed
+    /// on both sides.
+    pub fn sync_with(&mut self, mut remote: Box<dyn Remote>) -> Result<(), Error> {
+        self.syncing = None;
+        let in_bucket = self.selected_list().is_none();
+        let before = self.tasklists.len();
+        let mut state = read_sync_state(&self.db_path);
+        let report = sync(&mut self.tasklists, &mut state, &mut *remote)?;
+        write_sync_state(&self.db_path, &state)?;
+        self.keep_bucket_selected(in_bucket, self.tasklists.len() - before);
+        let removed: Vec<(TaskRef, Task)> = report
+            .removed
+            .into_iter()
+            .filter_map(|trashed| {
+                let list = self
+                    .tasklists
+                    .iter()
+                    .position(|l| l.id == trashed.list_id)?;
+                Some(((list, trashed.position), trashed.task))
+            })
+            .collect();
+        self.trash_tasks(removed);
+        for list in 0..self.tasklists.len() {
+            self.tasklists[list].prune_dependencies();
+            self.resort(list);
+        }
+        self.clamp_selection();
+        self.mark_dirty()?;
+        self.save()?;
+        self.flash_info(format!(
+            "Synced, pulled {} and pushed {} tasks",
+            report.pulled, report.pushed
+        ));
+        if !report.conflicts.is_empty() {
+            self.syncing = Some(Syncing {
+                remote,
+                state,
+                conflicts: report.conflicts,
+            });
+            self.ask_conflict();
+        }
+        Ok(())
+    }
+
+    /// Asks about the next conflict of the sync, offering the copy that was
+    /// edited last.
+    fn ask_conflict(&mut self) {
+        let conflict = match self.syncing.as_ref().and_then(|s| s.conflicts.first()) {
+            Some(conflict) => conflict,
+            None => {
+                self.syncing = None;
+                return;
+            }
+        };
+        let newer = if conflict.remote_newer {
+            "the server's"
+        } else {
+            "this one"
+        };
+        self.confirm = Some(Confirm {
+            question: format!(
+                "{} changed here and on the server, keep {}, the newer? (y/n)",
+                conflict.name, newer
+            ),
+            action: Pending::SyncConflict,
+        });
+    }
+
+    /// Keeps the newer or older copy of the first conflicting task, then
+    /// asks about the next.
+    fn settle_conflict(&mut self, keep_newer: bool) -> Result<(), Error> {
+        let mut syncing = match self.syncing.take() {
+            Some(syncing) => syncing,
+            None => return Ok(()),
+        };
+        let conflict = syncing.conflicts.remove(0);
+        let keep_local = conflict.remote_newer != keep_newer;
+        let list = self.tasklists.iter().position(|l| l.id == conflict.list);
+        resolve(
+            &mut self.tasklists,
+            &mut syncing.state,
+            &mut *syncing.remote,
+            conflict,
+            keep_local,
+        )?;
+        write_sync_state(&self.db_path, &syncing.state)?;
+        if let (Some(list), false) = (list, keep_local) {
+            self.mark_list_dirty(list)?;
+            self.save()?;
+        }
+        self.syncing = Some(syncing);
+        self.ask_conflict();
+        Ok(())
+    }
+
+    /// Moves the selected list out into the db at `path`, saving both.
+    pub fn move_list_to(&mut self, path: &Path) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => {
+                self.flash_error("Select a list to move".to_string());
+                return Ok(());
+            }
+        };
+        if self.tasklists.len() == 1 {
+            self.flash_error("Can't move the only list".to_string());
+            return Ok(());
+        }
+        if path == self.db_path {
+            self.flash_error("The list is already in that db".to_string());
+            return Ok(());
+        }
+        // The destination is written first so a failure loses nothing
+        append_list(path, self.tasklists[list].clone())?;
+        let entry = self.selected_entry();
+        let moved = self.tasklists.remove(list);
+        self.state.sort.remove(&moved.id);
+        self.list_state
+            .select(Some(entry.min(self.tasklists.len() - 1)));
+        self.task_state.select(None);
+        self.marked = None;
+        self.mark_dirty()?;
+        self.save()?;
+        // Undoing would bring back a list that's already in the other db
+        self.history.clear();
+        self.undo_point = None;
+        self.flash_info(format!("Moved {} to {}", moved.name, path.display()));
+        Ok(())
+    }
+
+    /// Swaps the selected list with its neighbour in the sidebar, keeping it
+    /// selected. Lists don't move past the pinned ones or the other way.
+    pub fn move_list(&mut self, up: bool) -> Result<(), Error> {
+        let selected = match self.selected_list() {
+            Some(selected) => selected,
+            None => return Ok(()),
+        };
+        if self.urgency_sort {
+            self.flash_error("Lists can't be moved while sorted by urgency".to_string());
+            return Ok(());
+        }
+        let order = self.sidebar_lists();
+        let entry = self.selected_entry();
+        let target = if up {
+            match entry.checked_sub(1) {
+                Some(target) => target,
+                None => return Ok(()),
+            }
+        } else if entry + 1 < order.len() {
+            entry + 1
+        } else {
+            return Ok(());
+        };
+        if self.tasklists[order[target]].pinned != self.tasklists[selected].pinned {
+            return Ok(());
+        }
+        self.tasklists.swap(selected, order[target]);
+        self.list_state.select(Some(target));
+        self.mark_dirty()
+    }
+
+    /// Pins the selected list to the top of the sidebar, or unpins it.
+    pub fn toggle_pinned(&mut self) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => return Ok(()),
+        };
+        self.tasklists[list].pinned = !self.tasklists[list].pinned;
+        self.list_state.select(Some(self.entry_of(list)));
+        self.mark_list_dirty(list)
+    }
+
+    /// Sorts the selected list by `key`, and with `auto` keeps it sorted as
+    /// its tasks change.
+    pub fn sort_tasks(&mut self, key: SortKey, auto: bool) -> Result<(), Error> {
+        let selected_list = match self.selected_list() {
+            Some(list) => list,
+            None => {
+                self.flash_error("Buckets are always sorted by due date".to_string());
+                return Ok(());
+            }
+        };
+        let order = SortOrder {
+            key,
+            descending: false,
+            auto,
+        };
+        self.set_sort(selected_list, order)
+    }
+
+    /// Flips the selected list's sort between ascending and descending.
+    pub fn reverse_sort(&mut self) -> Result<(), Error> {
+        let selected_list = match self.selected_list() {
+            Some(list) => list,
+            None => return Ok(()),
+        };
+        match self.state.sort.get(&self.tasklists[selected_list].id) {
+            Some(&order) => self.set_sort(
+                selected_list,
+                SortOrder {
+                    descending: !order.descending,
+                    ..order
+                },
+            ),
+            None => {
+                self.flash_error("This list isn't sorted, pick an order with :sort".to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Sorts the list and remembers the order for the next run.
+    fn set_sort(&mut self, list: usize, order: SortOrder) -> Result<(), Error> {
+        let tasks = &mut self.tasklists[list];
+        order.apply(&mut tasks.tasks);
+        self.state.sort.insert(tasks.id, order);
+        self.clamp_selection();
+        self.mark_list_dirty(list)
+    }
+
+    /// The mode keys go to. Several can be set up at once, like a search
+    /// in visual mode, and the innermost one wins.
+    pub fn mode(&self) -> Mode {
+        if self.search.is_some() {
+            Mode::Search
+        } else if self.finder.is_some() {
+            Mode::Find
+        } else if self.calendar.is_some() {
+            Mode::Calendar
+        } else if self.form.is_some() {
+            Mode::Form
+        } else if self.prompt.is_some() {
+            Mode::Editing
+        } else if self.confirm.is_some() {
+            Mode::Confirm
+        } else if self.checklist.is_some() {
+            Mode::Checklist
+        } else if self.tag_picker.is_some() {
+            Mode::TagPicker
+        } else if self.focus {
+            Mode::Focus
+        } else if self.board.is_some() {
+            Mode::Board
+        } else if self.marked.is_some() {
+            Mode::Visual
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// Leaves the current mode without acting on it, throwing away whatever
+    /// was typed. In Normal mode it clears the filters instead.
+    pub fn cancel(&mut self) {
+        match self.mode() {
+            Mode::Search => self.search = None,
+            Mode::Find => self.finder = None,
+            Mode::Calendar => self.calendar = None,
+            Mode::Form => self.form = None,
+            Mode::Editing => self.prompt = None,
+            Mode::Confirm => {
+                self.confirm = None;
+                // Conflicts left come up again on the next sync
+                self.syncing = None;
+            }
+            Mode::Checklist => self.checklist = None,
+            Mode::TagPicker => self.tag_picker = None,
+            Mode::Focus => self.focus = false,
+            Mode::Board => self.board = None,
+            Mode::Visual => self.marked = None,
+            Mode::Normal => self.clear_filters(),
+        }
+    }
+
+    /// Opens the new task form for the selected list.
+    pub fn open_form(&mut self) {
+        if self.selected_list().is_none() {
+            self.flash_error("Select a list to add to".to_string());
+            return;
+        }
+        self.form = Some(TaskForm::default());
+    }
+
+    /// Opens the task form filled in with the task, to edit it.
+    pub fn open_edit_form(&mut self, task: TaskRef) {
+        self.form = Some(TaskForm::for_task(
+            task,
+            self.task(task),
+            &self.config.date_format,
+        ));
+    }
+
+    /// Adds the task filled in on the form to the selected list, or updates
+    /// the one being edited, and selects it. The form stays open if
+    /// something can't be read.
+    pub fn submit_form(&mut self) -> Result<(), Error> {
+        let form = match &self.form {
+            Some(form) => form,
+            None => return Ok(()),
+        };
+        let list = match (form.editing, self.selected_list()) {
+            (Some((list, _)), _) | (None, Some(list)) => list,
+            (None, None) => return Ok(()),
+        };
+        let due = parse_duration(&self.config.snooze).unwrap_or_else(|| chrono::Duration::days(1));
+        let new = match form.parse(&self.config.date_format, due) {
+            Ok(new) => new,
+            Err(message) => {
+                self.flash_error(message);
+                return Ok(());
+            }
+        };
+        let id = match self.form.take().and_then(|form| form.editing) {
+            Some(task_ref) => {
+                let task = self.task_mut(task_ref);
+                task.name = new.name;
+                task.tags = new.tags;
+                task.start_date = new.start;
+                task.due_date = new.due;
+                task.id
+            }
+            None => {
+                let tasks = &mut self.tasklists[list].tasks;
+                let id = tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+                tasks.push(Task {
+                    tags: new.tags,
+                    start_date: new.start,
+                    ..Task::new(id, new.name, new.due)
+                });
+                id
+            }
+        };
+        self.mark_list_dirty(list)?;
+        let row = self
+            .visible_tasks()
+            .iter()
+            .position(|&(l, t)| l == list && self.tasklists[l].tasks[t].id == id);
+        if row.is_some() {
+            self.task_state.select(row);
+        }
+        Ok(())
+    }
+
+    pub fn open_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some(Prompt {
+            kind,
+            input: String::new(),
+        });
+    }
+
+    /// Closes the open question, going ahead with what it held back if `yes`.
+    pub fn answer_confirm(&mut self, yes: bool) -> Result<(), Error> {
+        let confirm = match self.confirm.take() {
+            Some(confirm) if yes || confirm.acts_on_no() => confirm,
+            _ => return Ok(()),
+        };
+        match confirm.action {
+            Pending::InsertList(name, index) => self.add_list(name, index),
+            Pending::Capture(name) => self.add_to_inbox(name),
+            Pending::DeleteTask(task) => {
+                self.delete_task(task)?;
+                self.save()
+            }
+            Pending::DeleteMarked => {
+                self.delete_marked()?;
+                self.save()
+            }
+            Pending::DeleteList(list) => self.delete_list(list),
+            Pending::SyncConflict => self.settle_conflict(yes),
+        }
+    }
+
+    /// Acts on the line typed into the prompt and closes it.
+    pub fn submit_prompt(&mut self) {
+        let prompt = match self.prompt.take() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        let result = match prompt.kind {
+            PromptKind::Estimate => match self.selected_task() {
+                Some(task) => {
+                    let estimate = parse_duration(&prompt.input).map(|d| d.num_seconds());
+                    self.set_estimate(task, estimate)
+                }
+                None => Ok(()),
+            },
+            PromptKind::Comment => match self.selected_task() {
+                Some(task) => self.add_comment(task, prompt.input.trim()),
+                None => Ok(()),
+            },
+            PromptKind::Subtask => match self.selected_task() {
+                Some(task) => self.add_subtask(task, prompt.input.trim()),
+                None => Ok(()),
+            },
+            PromptKind::Repeat => match self.selected_task() {
+                Some(task) => self.set_recurrence(task, prompt.input.trim()),
+                None => Ok(()),
+            },
+            PromptKind::Reminder => match self.selected_task() {
+                Some(task) => {
+                    let lead = parse_duration(&prompt.input).map(|d| d.num_seconds());
+                    self.set_reminder_lead(task, lead)
+                }
+                None => Ok(()),
+            },
+            PromptKind::Command => match parse_command(&prompt.input) {
+                Ok(command) => self.run_command(command),
+                Err(message) => {
+                    self.flash_error(message);
+                    Ok(())
+                }
+            },
+            PromptKind::GoToList => {
+                let names = self.tasklists.iter().map(|l| l.name.as_str());
+                match best_match(prompt.input.trim(), names) {
+                    Some(list) => {
+                        self.leave_tasks();
+                        self.list_state.select(Some(self.entry_of(list)));
+                    }
+                    None => self.flash_error(format!("No list matches {}", prompt.input.trim())),
+                }
+                Ok(())
+            }
+            PromptKind::Move => {
+                let names = self.tasklists.iter().map(|l| l.name.as_str());
+                match best_match(prompt.input.trim(), names) {
+                    Some(list) => self.move_tasks(list),
+                    None => {
+                        self.flash_error(format!("No list matches {}", prompt.input.trim()));
+                        Ok(())
+                    }
+                }
+            }
+            PromptKind::TagMarked => self.tag_marked(prompt.input.trim()),
+            PromptKind::NewListAbove | PromptKind::NewListBelow => {
+                let name = prompt.input.trim().to_string();
+                let index = match self.selected_list() {
+                    Some(list) if prompt.kind == PromptKind::NewListBelow => list + 1,
+                    Some(list) => list,
+                    None => self.tasklists.len(),
+                };
+                if name.is_empty() {
+                    Ok(())
+                } else {
+                    self.insert_list(name, index)
+                }
+            }
+            PromptKind::DependsOn => match self.selected_task() {
+                Some(task) if prompt.input.trim().is_empty() => self.clear_dependencies(task),
+                Some((list, task)) => {
+                    let tasks = &self.tasklists[list].tasks;
+                    let names = tasks.iter().map(|t| t.name.as_str());
+                    match best_match(prompt.input.trim(), names) {
+                        Some(dependency) => self.add_dependency((list, task), dependency),
+                        None => {
+                            self.flash_error(format!("No task matches {}", prompt.input.trim()));
+                            Ok(())
+                        }
+                    }
+                }
+                None => Ok(()),
+            },
+            PromptKind::TagFilter => {
+                self.set_tag_filter(&prompt.input);
+                Ok(())
+            }
+            PromptKind::Capture => match prompt.input.trim() {
+                "" => Ok(()),
+                name => self.capture(name.to_string()),
+            },
+        };
+        self.report(result);
+    }
+
+    /// Closes the search across lists and selects the highlighted result in
+    /// its list.
+    pub fn jump_to_found(&mut self) {
+        let found = match self.finder.take() {
+            Some(finder) => finder
+                .results(&self.tasklists)
+                .get(finder.selected)
+                .copied(),
+            None => return,
+        };
+        let task = match found {
+            Some(task) => task,
+            None => return,
+        };
+        self.leave_tasks();
+        self.list_state.select(Some(self.entry_of(task.0)));
+        match self.visible_tasks().iter().position(|&r| r == task) {
+            Some(row) => self.task_state.select(Some(row)),
+            None => {
+                let message = format!("{} is hidden by the filters", self.task(task).name);
+                self.flash_error(message);
+            }
+        }
+    }
+
+    /// Selects the next task matching the last search, or the previous one
+    /// unless `forward`, wrapping at the ends.
+    pub fn search_next(&mut self, forward: bool) {
+        let query = match &self.last_search {
+            Some(query) => query,
+            None => {
+                self.flash_error("No previous search".to_string());
+                return;
+            }
+        };
+        let matches: Vec<usize> = self
+            .visible_tasks()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &r)| fuzzy_match(query, &self.task(r).name).is_some())
+            .map(|(row, _)| row)
+            .collect();
+        let next = match self.task_state.selected() {
+            Some(row) if forward => matches.iter().find(|&&m| m > row).or(matches.first()),
+            Some(row) => matches.iter().rev().find(|&&m| m < row).or(matches.last()),
+            None if forward => matches.first(),
+            None => matches.last(),
+        };
+        match next {
+            Some(&row) => self.task_state.select(Some(row)),
+            None => self.flash_error(format!("No tasks match {}", query)),
+        }
+    }
+
+    /// Enters or leaves visual mode.
+    pub fn toggle_visual(&mut self) {
+        self.marked = match self.marked {
+            Some(_) => None,
+            None => Some(HashSet::new()),
+        };
+    }
+
+    pub fn toggle_mark(&mut self, task: TaskRef) {
+        if let Some(marked) = &mut self.marked {
+            if !marked.remove(&task) {
+                marked.insert(task);
+            }
+        }
+    }
+
+    /// Takes the marked tasks out of their lists, leaving visual mode. They
+    /// come back in order, with where each one was.
+    fn take_marked(&mut self) -> Vec<(TaskRef, Task)> {
+        let marked = self.marked.take().unwrap_or_default();
+        self.take_tasks(marked)
+    }
+
+    /// Takes the tasks out of their lists, in order and with where each one
+    /// was.
+    fn take_tasks(&mut self, tasks: HashSet<TaskRef>) -> Vec<(TaskRef, Task)> {
+        let mut refs: Vec<TaskRef> = tasks.into_iter().collect();
+        refs.sort_unstable();
+        // Removing from the back keeps the remaining indices valid
+        let taken = refs
+            .iter()
+            .rev()
+            .map(|&(list, task)| ((list, task), self.tasklists[list].tasks.remove(task)))
+            .collect::<Vec<_>>();
+        for list in &mut self.tasklists {
+            list.prune_dependencies();
+        }
+        self.clamp_selection();
+        taken.into_iter().rev().collect()
+    }
+
+    /// Puts deleted tasks in the trash, oldest first.
+    fn trash_tasks(&mut self, deleted: Vec<(TaskRef, Task)>) {
+        for ((list, position), task) in deleted {
+            self.trash.push(Trashed {
+                list_id: self.tasklists[list].id,
+                position,
+                task,
+            });
+        }
+        let overflow = self.trash.len().saturating_sub(TRASH_LIMIT);
+        self.trash.drain(..overflow);
+    }
+
+    /// Asks before deleting the task, which is then saved right away.
+    pub fn ask_delete_task(&mut self, task: TaskRef) {
+        self.confirm = Some(Confirm {
+            question: format!("Delete {}? (y/n)", self.task(task).name),
+            action: Pending::DeleteTask(task),
+        });
+    }
+
+    /// Asks before deleting the marked tasks, which is then saved right away.
+    pub fn ask_delete_marked(&mut self) {
+        let count = self.marked.as_ref().map_or(0, HashSet::len);
+        self.confirm = Some(Confirm {
+            question: format!("Delete {} marked tasks? (y/n)", count),
+            action: Pending::DeleteMarked,
+        });
+    }
+
+    /// Asks before deleting the selected list with all of its tasks.
+    pub fn ask_delete_list(&mut self) {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => return,
+        };
+        if self.tasklists.len() == 1 {
+            self.flash_error("Can't delete the only list".to_string());
+            return;
+        }
+        let list_ref = &self.tasklists[list];
+        self.confirm = Some(Confirm {
+            question: format!(
+                "Delete the list {} and its {} tasks for good? (y/n)",
+                list_ref.name,
+                list_ref.tasks.len()
+            ),
+            action: Pending::DeleteList(list),
+        });
+    }
+
+    /// Removes a list and its tasks, skipping the trash, and saves.
+    fn delete_list(&mut self, list: usize) -> Result<(), Error> {
+        let entry = self.selected_entry();
+        let deleted = self.tasklists.remove(list);
+        self.state.sort.remove(&deleted.id);
+        self.list_rows.remove(&deleted.id);
+        self.list_state
+            .select(Some(entry.min(self.tasklists.len() - 1)));
+        self.task_state.select(None);
+        self.marked = None;
+        self.flash_info(format!("Deleted the list {}", deleted.name));
+        self.mark_dirty()?;
+        self.save()
+    }
+
+    pub fn delete_marked(&mut self) -> Result<(), Error> {
+        let deleted = self.take_marked();
+        self.flash_info(format!("Deleted {} tasks, U to restore", deleted.len()));
+        self.trash_tasks(deleted);
+        self.mark_dirty()
+    }
+
+    /// Moves the task to the trash.
+    pub fn delete_task(&mut self, (list, task): TaskRef) -> Result<(), Error> {
+        let deleted = self.tasklists[list].tasks.remove(task);
+        self.tasklists[list].prune_dependencies();
+        self.clamp_selection();
+        self.flash_info(format!("Deleted {}, U to restore", deleted.name));
+        self.trash_tasks(vec![((list, task), deleted)]);
+        self.mark_list_dirty(list)
+    }
+
+    /// Moves the selected list's completed tasks to the trash.
+    pub fn clear_completed(&mut self) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => {
+                self.flash_error("Select a list to clear".to_string());
+                return Ok(());
+            }
+        };
+        let done: Vec<usize> = (0..self.tasklists[list].tasks.len())
+            .filter(|&task| self.tasklists[list].tasks[task].is_completed())
+            .collect();
+        // Removing from the back keeps the remaining indices valid
+        let mut cleared: Vec<(TaskRef, Task)> = done
+            .iter()
+            .rev()
+            .map(|&task| ((list, task), self.tasklists[list].tasks.remove(task)))
+            .collect();
+        cleared.reverse();
+        self.tasklists[list].prune_dependencies();
+        self.clamp_selection();
+        self.flash_info(format!("Cleared {} completed tasks", cleared.len()));
+        if cleared.is_empty() {
+            return Ok(());
+        }
+        self.trash_tasks(cleared);
+        self.mark_list_dirty(list)
+    }
+
+    /// Puts the most recently deleted task back where it was, selecting it if
+    /// the task panel has focus and it's on screen.
+    pub fn restore_deleted(&mut self) -> Result<(), Error> {
+        let list_id = match self.trash.last() {
+            Some(trashed) => trashed.list_id,
+            None => {
+                self.flash_error("Nothing to restore".to_string());
+                return Ok(());
+            }
+        };
+        let list = match self.tasklists.iter().position(|l| l.id == list_id) {
+            Some(list) => list,
+            None => {
+                self.flash_error("The list it was in is gone".to_string());
+                return Ok(());
+            }
+        };
+        let Trashed {
+            position, mut task, ..
+        } = self.trash.pop().expect("trash isn't empty");
+        let tasks = &mut self.tasklists[list].tasks;
+        // Its id may have been handed out again since
+        if tasks.iter().any(|t| t.id == task.id) {
+            task.id = tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+        }
+        let position = position.min(tasks.len());
+        let message = format!("Restored {}", task.name);
+        tasks.insert(position, task);
+        self.flash_info(message);
+        self.tasklists[list].prune_dependencies();
+        let row = self
+            .visible_tasks()
+            .iter()
+            .position(|&r| r == (list, position));
+        if row.is_some() && self.task_state.selected().is_some() {
+            self.task_state.select(row);
+        }
+        self.mark_list_dirty(list)
+    }
+
+    /// Moves the marked tasks, or the selected one outside visual mode, to
+    /// the end of `list` and saves.
+    pub fn move_tasks(&mut self, list: usize) -> Result<(), Error> {
+        if self.selected_list() == Some(list) {
+            self.flash_error("Tasks are already in that list".to_string());
+            return Ok(());
+        }
+        let tasks = match self.marked.take() {
+            Some(marked) => marked,
+            None => self.selected_task().into_iter().collect(),
+        };
+        let moved: Vec<Task> = self
+            .take_tasks(tasks)
+            .into_iter()
+            .map(|(_, task)| task)
+            .collect();
+        let count = moved.len();
+        let dest = &mut self.tasklists[list];
+        let first_id = dest.tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+        for (id, mut task) in (first_id..).zip(moved) {
+            task.id = id;
+            // Dependencies are ids in the list the task came from
+            task.depends_on.clear();
+            dest.tasks.push(task);
+        }
+        let message = format!(
+            "Moved {} task{} to {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            dest.name
+        );
+        self.flash_info(message);
+        self.resort(list);
+        self.mark_dirty()?;
+        self.save()
+    }
+
+    pub fn tag_marked(&mut self, tag: &str) -> Result<(), Error> {
+        if tag.is_empty() {
+            return Ok(());
+        }
+        let marked = self.marked.take().unwrap_or_default();
+        for &task in &marked {
+            let tags = &mut self.task_mut(task).tags;
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        self.flash_info(format!("Tagged {} tasks with {}", marked.len(), tag));
+        for &(list, _) in &marked {
+            self.mark_list_dirty(list)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `tag` to every task in the selected list that doesn't have it.
+    pub fn tag_list(&mut self, tag: &str) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => {
+                self.flash_error("Select a list to tag".to_string());
+                return Ok(());
+            }
+        };
+        let now = chrono::Utc::now();
+        let mut count = 0;
+        for task in &mut self.tasklists[list].tasks {
+            if !task.tags.iter().any(|t| t == tag) {
+                task.tags.push(tag.to_string());
+                task.updated_at = Some(now);
+                count += 1;
+            }
+        }
+        self.flash_info(format!("Tagged {} tasks with {}", count, tag));
+        if count == 0 {
+            return Ok(());
+        }
+        self.mark_list_dirty(list)
+    }
+
+    /// Moves the due date of every open task in the selected list by `by`,
+    /// unless that would put one before its start date.
+    pub fn shift_due(&mut self, by: chrono::Duration) -> Result<(), Error> {
+        let list = match self.selected_list() {
+            Some(list) => list,
+            None => {
+                self.flash_error("Select a list to shift".to_string());
+                return Ok(());
+            }
+        };
+        let open = || {
+            self.tasklists[list]
+                .tasks
+                .iter()
+                .filter(|t| !t.is_completed())
+        };
+        if open().any(|task| task.due_date + by < task.start_date) {
+            self.flash_error("That would put a due date before its start date".to_string());
+            return Ok(());
+        }
+        let now = chrono::Utc::now();
+        let mut count = 0;
+        for task in &mut self.tasklists[list].tasks {
+            if !task.is_completed() {
+                task.due_date += by;
+                task.updated_at = Some(now);
+                count += 1;
+            }
+        }
+        self.flash_info(format!("Moved {} due dates", count));
+        self.clamp_selection();
+        self.mark_list_dirty(list)
+    }
+
+    /// Opens the board on the selected task's column, or the first.
+    pub fn open_board(&mut self) {
+        let mut board = Board::default();
+        if let Some(task) = self.selected_task() {
+            let columns = board_columns(self);
+            board.column = Status::ALL
+                .iter()
+                .position(|&status| status == self.task(task).status)
+                .expect("every status has a column");
+            board.row = columns[board.column]
+                .iter()
+                .position(|&t| t == task)
+                .unwrap_or(0);
+        }
+        self.board = Some(board);
+    }
+
+    /// Closes the board, selecting the highlighted task in the task panel.
+    pub fn pick_board_task(&mut self) {
+        let task = self.board_task();
+        self.board = None;
+        let row = task.and_then(|task| self.visible_tasks().iter().position(|&r| r == task));
+        if row.is_some() {
+            self.task_state.select(row);
+        }
+    }
+
+    /// The task highlighted on the board, if its column has any.
+    pub fn board_task(&self) -> Option<TaskRef> {
+        let board = self.board?;
+        board_columns(self)[board.column].get(board.row).copied()
+    }
+
+    /// Moves the board's highlight by `columns` and `rows`, staying within
+    /// the board.
+    pub fn move_board(&mut self, columns: isize, rows: isize) {
+        let all = board_columns(self);
+        if let Some(board) = &mut self.board {
+            let column = board.column as isize + columns;
+            board.column = column.clamp(0, Status::ALL.len() as isize - 1) as usize;
+            let last = all[board.column].len().saturating_sub(1);
+            board.row = (board.row as isize + rows).clamp(0, last as isize) as usize;
+        }
+    }
+
+    /// Moves the highlighted task to the column `by` steps over, giving it
+    /// that status, and follows it there.
+    pub fn shift_board_task(&mut self, by: isize) -> Result<(), Error> {
+        let (board, task) = match (self.board, self.board_task()) {
+            (Some(board), Some(task)) => (board, task),
+            _ => return Ok(()),
+        };
+        let column = board.column as isize + by;
+        if column < 0 || column >= Status::ALL.len() as isize {
+            return Ok(());
+        }
+        self.set_status(task, Status::ALL[column as usize])?;
+        let status = self.task(task).status;
+        let column = Status::ALL
+            .iter()
+            .position(|&s| s == status)
+            .expect("every status has a column");
+        let row = board_columns(self)[column]
+            .iter()
+            .position(|&t| t == task)
+            .unwrap_or(0);
+        self.board = Some(Board { column, row });
+        Ok(())
+    }
+
+    /// Jumps to the agenda, or from it back to where it was opened.
+    pub fn toggle_agenda(&mut self) {
+        self.leave_tasks();
+        let entry = if self.view() == View::Agenda {
+            let back = self.before_agenda.take().unwrap_or(0);
+            back.min(self.sidebar_len() - 1)
+        } else {
+            self.before_agenda = Some(self.selected_entry());
+            self.sidebar_len() - 1
+        };
+        self.list_state.select(Some(entry));
+    }
+
+    pub fn open_calendar(&mut self) {
+        let day = self.day_filter.unwrap_or_else(|| Local::now().date_naive());
+        self.calendar = Some(day);
+    }
+
+    pub fn move_calendar(&mut self, days: i64) {
+        if let Some(day) = &mut self.calendar {
+            *day += chrono::Duration::days(days);
+        }
+    }
+
+    /// Moves the calendar's highlight by whole months, to the last day of the
+    /// month if it's shorter.
+    pub fn move_calendar_month(&mut self, months: i32) {
+        if let Some(day) = &mut self.calendar {
+            let moved = if months < 0 {
+                day.checked_sub_months(Months::new(months.unsigned_abs()))
+            } else {
+                day.checked_add_months(Months::new(months as u32))
+            };
+            *day = moved.unwrap_or(*day);
+        }
+    }
+
+    /// Closes the calendar, filtering the tasks to the highlighted day.
+    pub fn pick_calendar_day(&mut self) {
+        self.day_filter = self.calendar.take();
+        self.refilter();
+    }
+
+    /// Every tag in use, sorted, with differently cased spellings merged.
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        let all = self.tasklists.iter().flat_map(|list| &list.tasks);
+        for tag in all.flat_map(|task| &task.tags) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags
+    }
+
+    /// Tags matching the word being typed into a tag prompt, best first.
+    pub fn tag_suggestions(&self) -> Vec<String> {
+        let word = match &self.prompt {
+            Some(prompt)
+                if matches!(prompt.kind, PromptKind::TagFilter | PromptKind::TagMarked) =>
+            {
+                prompt.input.rsplit([' ', ',']).next().unwrap_or("")
+            }
+            _ => return Vec::new(),
+        };
+        let tags = self.all_tags();
+        if word.is_empty() {
+            return tags;
+        }
+        let mut scored: Vec<(i64, String)> = tags
+            .into_iter()
+            .filter_map(|tag| fuzzy_match(word, &tag).map(|(score, _)| (score, tag)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, tag)| tag).collect()
+    }
+
+    /// Replaces the word being typed into a tag prompt with the best match.
+    pub fn complete_tag(&mut self) {
+        let best = match self.tag_suggestions().into_iter().next() {
+            Some(best) => best,
+            None => return,
+        };
+        if let Some(prompt) = &mut self.prompt {
+            let start = prompt.input.rfind([' ', ',']).map_or(0, |at| at + 1);
+            prompt.input.truncate(start);
+            prompt.input.push_str(&best);
+        }
+    }
+
+    /// Filters the tasks to the space or comma separated tags in `input`,
+    /// keeping the AND/OR mode. Empty input drops the filter.
+    pub fn set_tag_filter(&mut self, input: &str) {
+        let tags: Vec<String> = input
+            .split([' ', ','])
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        let all = self.tag_filter.as_ref().is_some_and(|filter| filter.all);
+        self.tag_filter = if tags.is_empty() {
+            None
+        } else {
+            Some(TagFilter { tags, all })
+        };
+        self.refilter();
+    }
+
+    /// Opens the tag picker with the tags already filtered by picked.
+    pub fn open_tag_picker(&mut self) {
+        let tags = self.all_tags();
+        if tags.is_empty() {
+            self.flash_error("No tasks have tags".to_string());
+            return;
+        }
+        let chosen = self
+            .tag_filter
+            .as_ref()
+            .map_or_else(Vec::new, |filter| filter.tags.clone());
+        self.tag_picker = Some(TagPicker {
+            tags,
+            selected: 0,
+            chosen,
+        });
+    }
+
+    /// Picks the highlighted tag, or drops it if it was picked.
+    pub fn toggle_picked_tag(&mut self) {
+        if let Some(picker) = &mut self.tag_picker {
+            let tag = &picker.tags[picker.selected];
+            match picker
+                .chosen
+                .iter()
+                .position(|t| t.eq_ignore_ascii_case(tag))
+            {
+                Some(index) => {
+                    picker.chosen.remove(index);
+                }
+                None => picker.chosen.push(tag.clone()),
+            }
+        }
+    }
+
+    /// Closes the tag picker and filters by the picked tags, or just the
+    /// highlighted one if none were picked.
+    pub fn apply_tag_picker(&mut self) {
+        let picker = match self.tag_picker.take() {
+            Some(picker) => picker,
+            None => return,
+        };
+        let tags = if picker.chosen.is_empty() {
+            vec![picker.tags[picker.selected].clone()]
+        } else {
+            picker.chosen
+        };
+        let all = self.tag_filter.as_ref().is_some_and(|filter| filter.all);
+        self.tag_filter = Some(TagFilter { tags, all });
+        self.refilter();
+    }
+
+    /// Switches the tag filter between matching any and every tag.
+    pub fn toggle_tag_mode(&mut self) {
+        if let Some(filter) = &mut self.tag_filter {
+            filter.all = !filter.all;
+            self.refilter();
+        }
+    }
+
+    /// Moves to the first row after the filters changed which tasks are shown.
+    fn refilter(&mut self) {
+        self.task_state.select(if self.visible_tasks().is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+        self.marked = None;
+    }
+
+    /// Shows or hides completed tasks in lists, staying on the same task
+    /// if it's still shown.
+    pub fn toggle_hide_completed(&mut self) {
+        let selected = self.selected_task();
+        self.hide_completed = !self.hide_completed;
+        self.marked = None;
+        match selected.and_then(|task| self.visible_tasks().iter().position(|&r| r == task)) {
+            Some(row) => self.task_state.select(Some(row)),
+            None => self.clamp_selection(),
+        }
+    }
+
+    pub fn clear_filters(&mut self) {
+        let selected = self.selected_task();
+        self.day_filter = None;
+        self.tag_filter = None;
+        self.status_filter = None;
+        // Stay on the same task now that the rows have shifted
+        let row = selected.and_then(|task| self.visible_tasks().iter().position(|&r| r == task));
+        self.task_state.select(row);
+    }
+
+    /// Every list, or with `view` just the tasks on screen grouped under the
+    /// lists they come from.
+    fn export_lists(&self, view: bool) -> Vec<TaskList> {
+        if !view {
+            return self.tasklists.clone();
+        }
+        let mut lists: Vec<TaskList> = Vec::new();
+        for (list, task) in self.visible_tasks() {
+            let source = &self.tasklists[list];
+            let task = source.tasks[task].clone();
+            match lists.iter_mut().find(|l| l.id == source.id) {
+                Some(exported) => exported.tasks.push(task),
+                None => lists.push(TaskList {
+                    tasks: vec![task],
+                    ..TaskList::new(source.id, source.name.clone())
+                }),
+            }
+        }
+        lists
+    }
+
+    /// Writes every task to an iCalendar file beside the db, for calendar
+    /// apps to pick up.
+    pub fn export_calendar(&mut self) -> Result<(), Error> {
+        let path = self.db_path.with_file_name(ICS_FILE);
+        self.run_command(Command::ExportIcs(path, false))
+    }
+
+    pub fn run_command(&mut self, command: Command) -> Result<(), Error> {
+        match command {
+            Command::Quit => self.quit(true)?,
+            Command::ForceQuit => self.quit(false)?,
+            Command::Write => self.save()?,
+            Command::NewList(name) => self.new_list(name)?,
+            Command::Sort(key, auto) => self.sort_tasks(key, auto)?,
+            Command::ExportCsv(path, view) => {
+                let lists = self.export_lists(view);
+                export_csv(&lists, &path)?;
+                self.flash_info(format!("Exported to {}", path.display()));
+            }
+            Command::ExportYaml(path, view) => {
+                let lists = self.export_lists(view);
+                export_yaml(&lists, &path)?;
+                self.flash_info(format!("Exported to {}", path.display()));
+            }
+            Command::ExportIcs(path, view) => {
+                let lists = self.export_lists(view);
+                export_ics(&lists, &path)?;
+                self.flash_info(format!("Exported to {}", path.display()));
+            }
+            Command::ExportTaskwarrior(path, view) => {
+                let lists = self.export_lists(view);
+                export_taskwarrior(&lists, &path)?;
+                self.flash_info(format!("Exported to {}", path.display()));
+            }
+            Command::Collapse => self.toggle_collapsed(),
+            Command::TagList(tag) => self.tag_list(&tag)?,
+            Command::ClearCompleted => self.clear_completed()?,
+            Command::ToggleSpan => self.show_span = !self.show_span,
+            Command::ToggleCountdown => self.countdown = !self.countdown,
+            Command::MoveList(path) => self.move_list_to(&path)?,
+            Command::Import(format, path) => self.import_tasks(format, &path)?,
+            Command::ShiftDue(by) => self.shift_due(by)?,
+            Command::StatusFilter(status) => {
+                self.status_filter = Some(status);
+                self.refilter();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How pressing a list is, in percent: overdue tasks count fully and tasks
+/// due today half, out of the open tasks.
+fn urgency(list: &TaskList, now: chrono::DateTime<Local>) -> usize {
+    let open: Vec<&Task> = list.tasks.iter().filter(|t| !t.is_completed()).collect();
+    if open.is_empty() {
+        return 0;
+    }
+    let weight: usize = open
+        .iter()
+        .map(|task| match Bucket::of(task.due_date, now) {
+            Bucket::Overdue => 2,
+            Bucket::Today => 1,
+            Bucket::ThisWeek | Bucket::Later => 0,
+        })
+        .sum();
+    weight * 50 / open.len()
+}
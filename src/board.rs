@@ -0,0 +1,81 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem},
+};
+
+use crate::app::{App, TaskRef};
+use crate::db::Status;
+
+/// Where the highlight is on the board opened with `|`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Board {
+    /// Index into `Status::ALL` of the highlighted column
+    pub column: usize,
+    /// Highlighted row of the column
+    pub row: usize,
+}
+
+/// The tasks on screen in a column per status, each in display order.
+pub fn board_columns(app: &App) -> Vec<Vec<TaskRef>> {
+    let visible = app.visible_tasks();
+    Status::ALL
+        .iter()
+        .map(|&status| {
+            visible
+                .iter()
+                .copied()
+                .filter(|&task| app.task(task).status == status)
+                .collect()
+        })
+        .collect()
+}
+
+/// Draws one column of the board, for rendering with the highlighted row
+/// selected if it has the highlight.
+pub fn render_board_column<'a>(
+    app: &App,
+    status: Status,
+    tasks: &[TaskRef],
+    highlighted: bool,
+) -> List<'a> {
+    let theme = &app.theme;
+    let items: Vec<ListItem> = tasks
+        .iter()
+        .map(|&task| {
+            let list = &app.tasklists[task.0];
+            let task = app.task(task);
+            let style = if status == Status::Done {
+                Style::default().fg(theme.muted)
+            } else {
+                Style::default().fg(theme.text)
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(task.name.clone(), style),
+                Span::styled(
+                    format!(" ({})", list.name),
+                    Style::default().fg(theme.muted),
+                ),
+            ]))
+        })
+        .collect();
+    let border = if highlighted {
+        theme.active
+    } else {
+        theme.text
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border))
+                .title(format!("{} ({})", status.name(), tasks.len()))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.selected_bg)
+                .fg(theme.selected_fg)
+                .add_modifier(Modifier::BOLD),
+        )
+}
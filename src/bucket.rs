@@ -0,0 +1,46 @@
+use chrono::prelude::{DateTime, Local, Utc};
+use chrono::Duration;
+
+/// A sidebar entry gathering tasks from every list by how soon they're due.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bucket {
+    Overdue,
+    Today,
+    ThisWeek,
+    Later,
+}
+
+impl Bucket {
+    /// Every bucket, in the order they're listed under the real lists.
+    pub const ALL: [Bucket; 4] = [
+        Bucket::Overdue,
+        Bucket::Today,
+        Bucket::ThisWeek,
+        Bucket::Later,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Bucket::Overdue => "Overdue",
+            Bucket::Today => "Today",
+            Bucket::ThisWeek => "This Week",
+            Bucket::Later => "Later",
+        }
+    }
+
+    /// Picks the bucket for a due date. "This Week" covers the seven days
+    /// after today.
+    pub fn of(due: DateTime<Utc>, now: DateTime<Local>) -> Bucket {
+        let due = due.with_timezone(&Local);
+        let today = now.date_naive();
+        if due < now {
+            Bucket::Overdue
+        } else if due.date_naive() == today {
+            Bucket::Today
+        } else if due.date_naive() <= today + Duration::days(7) {
+            Bucket::ThisWeek
+        } else {
+            Bucket::Later
+        }
+    }
+}
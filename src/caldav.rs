@@ -0,0 +1,233 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use roxmltree::{Document, Node};
+
+use crate::config::CaldavConfig;
+use crate::db::Error;
+use crate::sync::{Calendar, Remote, RemoteTodo};
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:resourcetype/><d:displayname/><c:supported-calendar-component-set/></d:prop>
+</d:propfind>"#;
+
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter>
+</c:calendar-query>"#;
+
+fn sync_error(err: impl ToString) -> Error {
+    Error::SyncError(err.to_string())
+}
+
+fn xml_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn http_error(href: &str, err: Box<ureq::Error>) -> Error {
+    match *err {
+        ureq::Error::Status(412, _) => sync_error(format!(
+            "{} changed on the server during the sync, sync again",
+            href
+        )),
+        err => sync_error(err),
+    }
+}
+
+/// The first descendant of `node` called `name` in the namespace `ns`.
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|child| child.tag_name().namespace() == Some(ns) && child.tag_name().name() == name)
+}
+
+fn child_text(node: Node, ns: &str, name: &str) -> Option<String> {
+    Some(child(node, ns, name)?.text()?.trim().to_string())
+}
+
+/// Each `<response>` of a multistatus body.
+fn responses<'a, 'input>(doc: &'a Document<'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    doc.descendants().filter(|node| {
+        node.tag_name().namespace() == Some(DAV) && node.tag_name().name() == "response"
+    })
+}
+
+/// A CalDAV collection holding calendars, like Nextcloud's
+/// `remote.php/dav/calendars/<user>/` or Radicale's `/<user>/`.
+pub struct Caldav {
+    agent: ureq::Agent,
+    url: String,
+    /// Scheme and host the server's absolute paths are relative to
+    origin: String,
+    auth: String,
+}
+
+impl Caldav {
+    pub fn new(config: &CaldavConfig) -> Caldav {
+        let url = format!("{}/", config.url.trim_end_matches('/'));
+        let host_end = url
+            .find("://")
+            .and_then(|scheme| url[scheme + 3..].find('/').map(|path| scheme + 3 + path))
+            .unwrap_or(url.len());
+        let credentials = format!("{}:{}", config.username, config.password);
+        Caldav {
+            agent: ureq::Agent::new(),
+            origin: url[..host_end].to_string(),
+            url,
+            auth: format!("Basic {}", STANDARD.encode(credentials)),
+        }
+    }
+
+    fn absolute(&self, href: &str) -> String {
+        if href.contains("://") {
+            href.to_string()
+        } else {
+            format!("{}{}", self.origin, href)
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        href: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let mut request = self
+            .agent
+            .request(method, &self.absolute(href))
+            .set("Authorization", &self.auth);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        request.send_string(body).map_err(Box::new)
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        href: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> Result<ureq::Response, Error> {
+        self.request(method, href, headers, body)
+            .map_err(|err| http_error(href, err))
+    }
+
+    /// Sends a request with an XML body and reads the multistatus it
+    /// answers with.
+    fn xml(&self, method: &str, href: &str, body: &str) -> Result<String, Error> {
+        let headers = [
+            ("Depth", "1"),
+            ("Content-Type", "application/xml; charset=utf-8"),
+        ];
+        self.send(method, href, &headers, body)?
+            .into_string()
+            .map_err(sync_error)
+    }
+}
+
+impl Remote for Caldav {
+    fn calendars(&mut self) -> Result<Vec<Calendar>, Error> {
+        let body = self.xml("PROPFIND", &self.url, PROPFIND)?;
+        let doc = Document::parse(&body).map_err(sync_error)?;
+        let calendars = responses(&doc)
+            .filter(|response| {
+                let is_calendar = child(*response, CALDAV, "calendar").is_some();
+                // Calendars without a component set take anything
+                let takes_todos = child(*response, CALDAV, "supported-calendar-component-set")
+                    .is_none_or(|set| {
+                        set.children().all(|comp| !comp.is_element())
+                            || set
+                                .children()
+                                .any(|comp| comp.attribute("name") == Some("VTODO"))
+                    });
+                is_calendar && takes_todos
+            })
+            .filter_map(|response| {
+                let href = child_text(response, DAV, "href")?;
+                let name = child_text(response, DAV, "displayname")
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| {
+                        href.trim_end_matches('/')
+                            .rsplit('/')
+                            .next()
+                            .unwrap_or_default()
+                            .to_string()
+                    });
+                Some(Calendar {
+                    href: self.absolute(&href),
+                    name,
+                })
+            })
+            .collect();
+        Ok(calendars)
+    }
+
+    fn create_calendar(&mut self, name: &str) -> Result<Calendar, Error> {
+        let href = format!("{}tbg-{:016x}/", self.url, rand::random::<u64>());
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:mkcalendar xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:set><d:prop>
+    <d:displayname>{}</d:displayname>
+    <c:supported-calendar-component-set><c:comp name="VTODO"/></c:supported-calendar-component-set>
+  </d:prop></d:set>
+</c:mkcalendar>"#,
+            xml_text(name)
+        );
+        let headers = [("Content-Type", "application/xml; charset=utf-8")];
+        self.send("MKCALENDAR", &href, &headers, &body)?;
+        Ok(Calendar {
+            href,
+            name: name.to_string(),
+        })
+    }
+
+    fn todos(&mut self, calendar: &Calendar) -> Result<Vec<RemoteTodo>, Error> {
+        let body = self.xml("REPORT", &calendar.href, CALENDAR_QUERY)?;
+        let doc = Document::parse(&body).map_err(sync_error)?;
+        let todos = responses(&doc)
+            .filter_map(|response| {
+                Some(RemoteTodo {
+                    href: self.absolute(&child_text(response, DAV, "href")?),
+                    etag: child_text(response, DAV, "getetag")?,
+                    ics: child(response, CALDAV, "calendar-data")?
+                        .text()?
+                        .to_string(),
+                })
+            })
+            .collect();
+        Ok(todos)
+    }
+
+    fn put(&mut self, href: &str, ics: &str, etag: Option<&str>) -> Result<String, Error> {
+        let mut headers = vec![("Content-Type", "text/calendar; charset=utf-8")];
+        match etag {
+            // Without an etag from the last upload there's nothing to match
+            Some("") => {}
+            Some(etag) => headers.push(("If-Match", etag)),
+            None => headers.push(("If-None-Match", "*")),
+        }
+        let response = self.send("PUT", href, &headers, ics)?;
+        // Servers that change what they're sent don't return an etag, so
+        // the next sync just pulls the to-do back down
+        Ok(response.header("ETag").unwrap_or_default().to_string())
+    }
+
+    fn delete(&mut self, href: &str, etag: &str) -> Result<(), Error> {
+        let condition = [("If-Match", etag)];
+        let headers: &[(&str, &str)] = if etag.is_empty() { &[] } else { &condition };
+        match self.request("DELETE", href, headers, "") {
+            // Already gone
+            Ok(_) => Ok(()),
+            Err(err) if matches!(*err, ureq::Error::Status(404, _)) => Ok(()),
+            Err(err) => Err(http_error(href, err)),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use chrono::prelude::{Datelike, Local, NaiveDate};
+use chrono::Duration;
+use std::collections::HashMap;
+use tui::{
+    layout::Constraint,
+    style::{Modifier, Style},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Cell, Row, Table},
+};
+
+use crate::db::TaskList;
+use crate::theme::Theme;
+
+/// Counts every task by the local date it's due on.
+pub fn due_counts(lists: &[TaskList]) -> HashMap<NaiveDate, usize> {
+    let mut counts = HashMap::new();
+    for task in lists.iter().flat_map(|list| &list.tasks) {
+        *counts
+            .entry(task.due_date.with_timezone(&Local).date_naive())
+            .or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Draws the month containing `selected` as a Monday-first grid.
+pub fn render_calendar<'a>(
+    selected: NaiveDate,
+    counts: &HashMap<NaiveDate, usize>,
+    theme: &Theme,
+) -> Table<'a> {
+    let first = selected.with_day(1).expect("every month has a first day");
+    let today = Local::now().date_naive();
+
+    let mut rows = Vec::new();
+    let mut day = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    while day.month() == first.month() || day < first {
+        let cells = (0..7)
+            .map(|_| {
+                let cell = if day.month() != first.month() {
+                    Cell::from("")
+                } else {
+                    let text = match counts.get(&day) {
+                        Some(count) => format!("{:>2} ({})", day.day(), count),
+                        None => format!("{:>2}", day.day()),
+                    };
+                    let mut style = Style::default();
+                    if counts.contains_key(&day) {
+                        style = style.fg(theme.active);
+                    }
+                    if day == today {
+                        style = style.add_modifier(Modifier::UNDERLINED);
+                    }
+                    if day == selected {
+                        style = style
+                            .bg(theme.selected_bg)
+                            .fg(theme.selected_fg)
+                            .add_modifier(Modifier::BOLD);
+                    }
+                    Cell::from(Span::styled(text, style))
+                };
+                day += Duration::days(1);
+                cell
+            })
+            .collect::<Vec<Cell>>();
+        rows.push(Row::new(cells).height(2));
+    }
+
+    let header = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]
+        .iter()
+        .map(|d| {
+            Cell::from(Span::styled(
+                d.to_owned(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        })
+        .collect::<Vec<Cell>>();
+
+    Table::new(rows)
+        .header(Row::new(header))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(theme.text))
+                .title(first.format("%B %Y").to_string())
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[Constraint::Ratio(1, 7); 7])
+}
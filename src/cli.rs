@@ -0,0 +1,335 @@
+use chrono::prelude::Utc;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::app::INBOX;
+use crate::bucket::Bucket;
+use crate::config::{is_valid_date_format, Config, DEFAULT_DATE_FORMAT};
+use crate::core::{add_task, complete_task, find_or_add_list, remove_task, TaskId};
+use crate::db::{
+    demo_lists, read_db, write_db, Task, TaskList, DEMO_FILE, SPLIT_DIR, SQLITE_FILE, YAML_FILE,
+};
+use crate::duration::parse_duration;
+use crate::export::export_ics;
+use crate::form::parse_date;
+use crate::headless::{dump_db, print_tasks, ListOptions};
+use crate::import::{import_file, ImportFormat};
+use crate::store::backend_file;
+use crate::taskwarrior::export_taskwarrior;
+use crate::trash::{read_trash, write_trash, TRASH_LIMIT};
+
+fn flag(name: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(name)
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
+/// The filters of `list`, which the old `--list` flag takes too.
+fn list_args(hide: bool) -> [Arg<'static>; 4] {
+    [
+        Arg::new("due")
+            .long("due")
+            .value_name("WHEN")
+            .value_parser(["overdue", "today", "week", "later"])
+            .help("Only open tasks due then")
+            .hide(hide),
+        Arg::new("tag")
+            .long("tag")
+            .value_name("TAG")
+            .help("Only tasks with this tag")
+            .hide(hide),
+        Arg::new("list-name")
+            .long("list-name")
+            .value_name("LIST")
+            .help("Only tasks in this list")
+            .hide(hide),
+        flag("json", "Print JSON instead of tab separated lines").hide(hide),
+    ]
+}
+
+fn id_arg() -> Arg<'static> {
+    Arg::new("id")
+        .required(true)
+        .value_parser(|id: &str| id.parse::<TaskId>())
+        .help("The task's id, as printed by `tbg list`")
+}
+
+/// Flags picking the db and the UI's options, and subcommands that work on
+/// the db without starting the UI.
+pub fn command() -> Command<'static> {
+    Command::new("tbg")
+        .about("Tasks But Good, a task manager for the terminal")
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("db")
+                .long("db")
+                .value_name("PATH")
+                .global(true)
+                .help("Use the db at PATH, picking the backend by its extension"),
+        )
+        .arg(flag("sqlite", "Use the SQLite db in the data directory").global(true))
+        .arg(flag("yaml", "Use the YAML db in the data directory").global(true))
+        .arg(flag("split", "Use a file per list in the data directory").global(true))
+        .arg(flag("demo", "Start from a fresh copy of the sample tasks").global(true))
+        .arg(flag("verbose", "Also log key presses").global(true))
+        // How `list` was spelled before there were subcommands
+        .arg(flag("list", "Print tasks").hide(true))
+        .args(list_args(true))
+        .subcommand(
+            Command::new("add")
+                .about("Add a task")
+                .arg(Arg::new("name").required(true).help("What to do"))
+                .arg(
+                    Arg::new("list")
+                        .long("list")
+                        .value_name("LIST")
+                        .help("List to add it to, made if there's none [default: Inbox]"),
+                )
+                .arg(
+                    Arg::new("due")
+                        .long("due")
+                        .value_name("DATE")
+                        .help("When it's due, as typed into the new task form"),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .value_name("TAG")
+                        .action(ArgAction::Append)
+                        .help("Tag it, can be given more than once"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Print tasks with the ids `done` and `rm` take")
+                .args(list_args(false)),
+        )
+        .subcommand(Command::new("done").about("Check off a task").arg(id_arg()))
+        .subcommand(
+            Command::new("rm")
+                .about("Delete a task, which can be restored in the app")
+                .arg(id_arg()),
+        )
+        .subcommand(
+            Command::new("import")
+                .long_flag_alias("import")
+                .about("Add the tasks in another app's file, or - for stdin")
+                .arg(
+                    Arg::new("format")
+                        .required(true)
+                        .value_parser(["todotxt", "taskwarrior"]),
+                )
+                .arg(Arg::new("file").required(true)),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write every task for another app, to a file or - for stdout")
+                .arg(
+                    Arg::new("format")
+                        .required(true)
+                        .value_parser(["ics", "taskwarrior"]),
+                )
+                .arg(Arg::new("file").required(true)),
+        )
+        .subcommand(Command::new("dump").about("Print the whole db as JSON"))
+}
+
+/// The db the flags ask for, or the one of the configured backend in `data`.
+pub fn db_path(
+    matches: &ArgMatches,
+    config: &Config,
+    data: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(if let Some(path) = matches.get_one::<String>("db") {
+        PathBuf::from(path)
+    } else if matches.get_flag("sqlite") {
+        data.join(SQLITE_FILE)
+    } else if matches.get_flag("yaml") {
+        data.join(YAML_FILE)
+    } else if matches.get_flag("split") {
+        data.join(SPLIT_DIR)
+    } else if matches.get_flag("demo") {
+        // Every demo starts from the same sample tasks
+        let path = data.join(DEMO_FILE);
+        write_db(&path, &demo_lists())?;
+        path
+    } else {
+        let file = backend_file(&config.backend)
+            .ok_or_else(|| format!("Unknown backend in config: {}", config.backend))?;
+        data.join(file)
+    })
+}
+
+fn list_options(matches: &ArgMatches, ids: bool) -> ListOptions {
+    let text = |id: &str| matches.get_one::<String>(id).cloned();
+    ListOptions {
+        due: text("due").map(|due| match due.as_str() {
+            "overdue" => Bucket::Overdue,
+            "today" => Bucket::Today,
+            "week" => Bucket::ThisWeek,
+            _ => Bucket::Later,
+        }),
+        tag: text("tag"),
+        list_name: text("list-name"),
+        json: matches.get_flag("json"),
+        ids,
+    }
+}
+
+/// The list and task index of the task the `id` argument names.
+fn find_task(matches: &ArgMatches, lists: &[TaskList]) -> Result<(usize, usize), String> {
+    let id = *matches.get_one::<TaskId>("id").expect("id is required");
+    id.find(lists).ok_or_else(|| format!("No task {}", id))
+}
+
+fn add(
+    matches: &ArgMatches,
+    db_path: &Path,
+    config: &Config,
+    date_format: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut lists = read_db(db_path)?;
+    let name = matches.get_one::<String>("name").expect("name is required");
+    let snooze = parse_duration(&config.snooze).unwrap_or_else(|| chrono::Duration::days(1));
+    let due = match matches.get_one::<String>("due") {
+        Some(due) => parse_date(due, date_format, Utc::now())
+            .ok_or_else(|| format!("Can't read the due date {}", due))?,
+        None => Utc::now() + snooze,
+    };
+    let mut task = Task::new(0, name.clone(), due);
+    if let Some(tags) = matches.get_many::<String>("tag") {
+        task.tags = tags.cloned().collect();
+    }
+    let list_name = matches
+        .get_one::<String>("list")
+        .map_or(INBOX, String::as_str);
+    let (list, _) = find_or_add_list(&mut lists, list_name);
+    let id = TaskId {
+        list: lists[list].id,
+        task: add_task(&mut lists[list], task),
+    };
+    write_db(db_path, &lists)?;
+    println!("Added {} to {} as {}", name, lists[list].name, id);
+    Ok(())
+}
+
+/// Runs the subcommand, or the old `--list`, on the db at `db_path`.
+/// Returns whether there was one, otherwise it's the UI's turn.
+pub fn run(matches: &ArgMatches, db_path: &Path, config: &Config) -> Result<bool, Box<dyn Error>> {
+    let date_format = if is_valid_date_format(&config.date_format) {
+        config.date_format.as_str()
+    } else {
+        DEFAULT_DATE_FORMAT
+    };
+    let (name, sub) = match matches.subcommand() {
+        Some(subcommand) => subcommand,
+        None if matches.get_flag("list") => {
+            print_tasks(
+                &read_db(db_path)?,
+                &list_options(matches, false),
+                date_format,
+            )?;
+            return Ok(true);
+        }
+        None => return Ok(false),
+    };
+    match name {
+        "add" => add(sub, db_path, config, date_format)?,
+        "list" => print_tasks(&read_db(db_path)?, &list_options(sub, true), date_format)?,
+        "done" => {
+            let mut lists = read_db(db_path)?;
+            let (list, task) = find_task(sub, &lists)?;
+            let name = lists[list].tasks[task].name.clone();
+            if !complete_task(&mut lists[list], task, Utc::now()) {
+                return Err(format!("{} is blocked by unfinished tasks", name).into());
+            }
+            write_db(db_path, &lists)?;
+            println!("Finished {}", name);
+        }
+        "rm" => {
+            let mut lists = read_db(db_path)?;
+            let (list, task) = find_task(sub, &lists)?;
+            let trashed = remove_task(&mut lists[list], task);
+            println!("Deleted {}", trashed.task.name);
+            write_db(db_path, &lists)?;
+            if config.keep_trash {
+                let mut trash = read_trash(db_path);
+                trash.push(trashed);
+                let overflow = trash.len().saturating_sub(TRASH_LIMIT);
+                trash.drain(..overflow);
+                write_trash(db_path, &trash)?;
+            }
+        }
+        "import" => {
+            let format = sub.get_one::<String>("format").expect("format is required");
+            let format = ImportFormat::parse(format).expect("clap checks the format");
+            let file = PathBuf::from(sub.get_one::<String>("file").expect("file is required"));
+            let mut lists = read_db(db_path)?;
+            let (tasks, _) = import_file(&mut lists, format, &file)?;
+            write_db(db_path, &lists)?;
+            println!("Imported {} tasks into {}", tasks, db_path.display());
+        }
+        "export" => {
+            let format = sub.get_one::<String>("format").expect("format is required");
+            let file = PathBuf::from(sub.get_one::<String>("file").expect("file is required"));
+            let lists = read_db(db_path)?;
+            match format.as_str() {
+                "ics" => export_ics(&lists, &file)?,
+                _ => export_taskwarrior(&lists, &file)?,
+            }
+            if file != Path::new("-") {
+                println!("Exported to {}", file.display());
+            }
+        }
+        "dump" => dump_db(&read_db(db_path)?)?,
+        _ => unreachable!("clap only lets through the subcommands above"),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::scratch_db;
+    use std::fs;
+
+    #[test]
+    fn subcommands_add_finish_and_delete_tasks_by_id() {
+        let path = scratch_db();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let personal = TaskList {
+            tasks: vec![Task::new(0, "a".to_string(), Utc::now())],
+            ..TaskList::new(4, "Personal".to_string())
+        };
+        write_db(&path, &[personal]).unwrap();
+        let config = Config {
+            keep_trash: true,
+            ..Config::default()
+        };
+        let tbg = |args: &[&str]| {
+            let matches = command()
+                .try_get_matches_from(std::iter::once("tbg").chain(args.iter().copied()))
+                .unwrap();
+            run(&matches, &path, &config).unwrap()
+        };
+        assert!(!tbg(&[]));
+        assert!(tbg(&[
+            "add", "Buy milk", "--list", "personal", "--tag", "shop"
+        ]));
+        assert!(tbg(&["add", "Call Mom", "--due", "2d"]));
+        let lists = read_db(&path).unwrap();
+        assert_eq!(lists[0].tasks[1].name, "Buy milk");
+        assert_eq!(lists[0].tasks[1].tags, ["shop"]);
+        assert_eq!((lists[1].id, lists[1].name.as_str()), (5, "Inbox"));
+        assert!(tbg(&["done", "4.1"]));
+        assert!(tbg(&["rm", "5.0"]));
+        let lists = read_db(&path).unwrap();
+        assert!(lists[0].tasks[1].is_completed());
+        assert!(lists[1].tasks.is_empty());
+        assert_eq!(read_trash(&path)[0].task.name, "Call Mom");
+        let bad = command().try_get_matches_from(["tbg", "done", "5"]);
+        assert!(bad.is_err());
+    }
+}
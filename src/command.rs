@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::db::{data_dir, Status};
+use crate::duration::parse_duration;
+use crate::import::ImportFormat;
+
+/// Field a list's tasks can be ordered by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    Start,
+    Due,
+    /// Most urgent first
+    Priority,
+}
+
+/// An action typed into the `:` prompt.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Command {
+    Quit,
+    ForceQuit,
+    Write,
+    NewList(String),
+    /// Sort the selected list, and keep it sorted if the flag is set
+    Sort(SortKey, bool),
+    /// Export to a path, only the tasks on screen if the flag is set
+    ExportCsv(PathBuf, bool),
+    ExportYaml(PathBuf, bool),
+    ExportIcs(PathBuf, bool),
+    ExportTaskwarrior(PathBuf, bool),
+    Collapse,
+    /// Show only tasks with this status
+    StatusFilter(Status),
+    /// Add a tag to every task in the selected list
+    TagList(String),
+    /// Move the selected list's completed tasks to the trash
+    ClearCompleted,
+    /// Show or hide the column with the time from start to due date
+    ToggleSpan,
+    /// Switch the due column between dates and the time left
+    ToggleCountdown,
+    /// Move the selected list into another db
+    MoveList(PathBuf),
+    /// Add the tasks in a file from another app
+    Import(ImportFormat, PathBuf),
+    /// Move the due dates of the selected list's open tasks
+    ShiftDue(chrono::Duration),
+}
+
+/// A bare name like `work` is a JSON db next to the default one, anything
+/// with an extension is taken as a path.
+fn profile_path(profile: &str) -> PathBuf {
+    let path = PathBuf::from(profile);
+    if path.extension().is_some() {
+        path
+    } else {
+        data_dir().join(format!("{}.json", profile))
+    }
+}
+
+/// Parses a command line like `new list Work` or `sort due`.
+pub fn parse_command(input: &str) -> Result<Command, String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    match words.as_slice() {
+        ["q"] | ["quit"] | ["wq"] => Ok(Command::Quit),
+        ["q!"] | ["quit!"] => Ok(Command::ForceQuit),
+        ["w"] | ["write"] => Ok(Command::Write),
+        ["new", "list", name @ ..] if !name.is_empty() => Ok(Command::NewList(name.join(" "))),
+        ["sort", key] | ["sort", key, "auto"] => {
+            let auto = words.len() == 3;
+            match *key {
+                "name" => Ok(Command::Sort(SortKey::Name, auto)),
+                "start" => Ok(Command::Sort(SortKey::Start, auto)),
+                "due" => Ok(Command::Sort(SortKey::Due, auto)),
+                "priority" => Ok(Command::Sort(SortKey::Priority, auto)),
+                _ => Err(format!("Can't sort by {}", key)),
+            }
+        }
+        ["export", "csv", path] => Ok(Command::ExportCsv(PathBuf::from(path), false)),
+        ["export", "yaml", path] => Ok(Command::ExportYaml(PathBuf::from(path), false)),
+        ["export", "view", "csv", path] => Ok(Command::ExportCsv(PathBuf::from(path), true)),
+        ["export", "view", "yaml", path] => Ok(Command::ExportYaml(PathBuf::from(path), true)),
+        ["export", "ics", path] => Ok(Command::ExportIcs(PathBuf::from(path), false)),
+        ["export", "view", "ics", path] => Ok(Command::ExportIcs(PathBuf::from(path), true)),
+        ["export", "taskwarrior", path] => {
+            Ok(Command::ExportTaskwarrior(PathBuf::from(path), false))
+        }
+        ["export", "view", "taskwarrior", path] => {
+            Ok(Command::ExportTaskwarrior(PathBuf::from(path), true))
+        }
+        ["collapse"] => Ok(Command::Collapse),
+        ["clear"] => Ok(Command::ClearCompleted),
+        ["span"] => Ok(Command::ToggleSpan),
+        ["countdown"] => Ok(Command::ToggleCountdown),
+        ["move", "list", profile] => Ok(Command::MoveList(profile_path(profile))),
+        ["import", format, path] => ImportFormat::parse(format)
+            .map(|format| Command::Import(format, PathBuf::from(path)))
+            .ok_or_else(|| format!("Can't import {}", format)),
+        ["shift", offset] => {
+            let (sign, duration) = match offset.strip_prefix('-') {
+                Some(duration) => (-1, duration),
+                None => (1, offset.trim_start_matches('+')),
+            };
+            parse_duration(duration)
+                .map(|by| Command::ShiftDue(by * sign))
+                .ok_or_else(|| format!("Can't shift by {}", offset))
+        }
+        ["tag", "list", tag] => Ok(Command::TagList(tag.to_string())),
+        ["status", status] => match *status {
+            "todo" => Ok(Command::StatusFilter(Status::Todo)),
+            "progress" => Ok(Command::StatusFilter(Status::InProgress)),
+            "waiting" => Ok(Command::StatusFilter(Status::Waiting)),
+            "done" => Ok(Command::StatusFilter(Status::Done)),
+            _ => Err(format!("Unknown status: {}", status)),
+        },
+        [] => Err("No command given".to_string()),
+        _ => Err(format!("Unknown command: {}", input.trim())),
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::format::{Item, StrftimeItems};
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{env, fs};
+
+/// Where the config was read from before it moved to the user's config
+/// directory, still used if it's the only one there is
+const OLD_CONFIG_PATH: &str = "./data/config.toml";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Initial width of the list panel, in percent
+    pub list_width: u16,
+    /// Whether j/k wrap around at the ends of a list
+    pub wrap_around: bool,
+    /// strftime pattern used to display task dates
+    pub date_format: String,
+    /// How far `+` pushes a task's due date, e.g. "1d" or "4h"
+    pub snooze: String,
+    /// Due dates closer than this are shown in orange
+    pub due_soon: String,
+    /// Due dates closer than this are shown in yellow, later ones in green
+    pub due_near: String,
+    /// Whether the due column counts down, like "in 3 hours", instead of
+    /// showing dates
+    pub countdown: bool,
+    /// How long before a task is due to remind about it, e.g. "15m"
+    pub reminder_lead: String,
+    /// Open tasks not edited for this long are shown as stale, "0" for never
+    pub stale_after: String,
+    /// Whether deleted tasks can still be restored after quitting
+    pub keep_trash: bool,
+    /// Whether every change is saved straight away instead of on `:w`
+    pub autosave: bool,
+    /// Where tasks are kept unless a flag says otherwise: "json", "yaml",
+    /// "split" or "sqlite"
+    pub backend: String,
+    /// Action names mapped to the keys that replace their defaults
+    pub keys: HashMap<String, String>,
+    /// Color preset: "dark", "light" or "solarized"
+    pub theme: String,
+    /// Theme roles mapped to colors that replace the preset's
+    pub colors: HashMap<String, String>,
+    /// Server the sync key talks to, if any
+    pub caldav: Option<CaldavConfig>,
+    /// Todoist account the sync key talks to, if any
+    pub todoist: Option<TodoistConfig>,
+}
+
+/// The `[caldav]` table: the collection holding the user's calendars and
+/// the account to log in with. It's still read without the `caldav` feature,
+/// so syncing can say why it won't.
+#[derive(Deserialize, Clone)]
+#[cfg_attr(not(feature = "caldav"), allow(dead_code))]
+pub struct CaldavConfig {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+/// The `[todoist]` table: the API token from Todoist's integration settings.
+#[derive(Deserialize, Clone)]
+#[cfg_attr(not(feature = "todoist"), allow(dead_code))]
+pub struct TodoistConfig {
+    pub token: String,
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Checks a strftime pattern so a typo doesn't render garbage.
+pub fn is_valid_date_format(format: &str) -> bool {
+    !format.is_empty() && StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            list_width: 20,
+            wrap_around: false,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            snooze: "1d".to_string(),
+            due_soon: "3h".to_string(),
+            due_near: "1d".to_string(),
+            countdown: false,
+            reminder_lead: "0".to_string(),
+            stale_after: "14d".to_string(),
+            keep_trash: true,
+            autosave: false,
+            backend: "json".to_string(),
+            keys: HashMap::new(),
+            theme: "dark".to_string(),
+            colors: HashMap::new(),
+            caldav: None,
+            todoist: None,
+        }
+    }
+}
+
+/// The user's config directory: `$XDG_CONFIG_HOME`, `~/.config`, or
+/// `%APPDATA%` on Windows.
+fn config_dir() -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+}
+
+/// `tbg/config.toml` in the user's config directory, unless only the old
+/// `./data/config.toml` exists.
+pub fn config_path() -> PathBuf {
+    let path = config_dir().map(|dir| dir.join("tbg").join("config.toml"));
+    match path {
+        Some(path) if path.exists() || !PathBuf::from(OLD_CONFIG_PATH).exists() => path,
+        _ => PathBuf::from(OLD_CONFIG_PATH),
+    }
+}
+
+pub fn read_config() -> Config {
+    let path = config_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Config::default(),
+    };
+    toml::from_str(&content).unwrap_or_else(|err| {
+        warn!("couldn't parse {}, using defaults: {}", path.display(), err);
+        Config::default()
+    })
+}
@@ -0,0 +1,101 @@
+use chrono::prelude::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::db::{Status, Task, TaskList};
+use crate::recurrence::spawn_recurring;
+use crate::trash::Trashed;
+
+/// A task's list id and id within it, written `3.14` on the command line.
+/// Unlike positions these don't change as tasks are added and sorted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TaskId {
+    pub list: usize,
+    pub task: usize,
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.list, self.task)
+    }
+}
+
+impl FromStr for TaskId {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<TaskId, String> {
+        let parse = || {
+            let (list, task) = id.split_once('.')?;
+            Some(TaskId {
+                list: list.parse().ok()?,
+                task: task.parse().ok()?,
+            })
+        };
+        parse().ok_or_else(|| format!("Task ids look like 2.5, not {}", id))
+    }
+}
+
+impl TaskId {
+    /// Where the task is in `lists`, as list and task index.
+    pub fn find(self, lists: &[TaskList]) -> Option<(usize, usize)> {
+        let list = lists.iter().position(|list| list.id == self.list)?;
+        let task = lists[list]
+            .tasks
+            .iter()
+            .position(|task| task.id == self.task)?;
+        Some((list, task))
+    }
+}
+
+/// The index of the list called `name`, ignoring case, adding it at the end
+/// if there's none. Also returns whether it was added.
+pub fn find_or_add_list(lists: &mut Vec<TaskList>, name: &str) -> (usize, bool) {
+    match lists
+        .iter()
+        .position(|list| list.name.eq_ignore_ascii_case(name))
+    {
+        Some(list) => (list, false),
+        None => {
+            let id = lists.iter().map(|l| l.id + 1).max().unwrap_or(0);
+            lists.push(TaskList::new(id, name.to_string()));
+            (lists.len() - 1, true)
+        }
+    }
+}
+
+/// Adds `task` to the end of `list` under the next free id, which is
+/// returned.
+pub fn add_task(list: &mut TaskList, mut task: Task) -> usize {
+    let id = list.tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+    task.id = id;
+    list.tasks.push(task);
+    id
+}
+
+/// Checks off the task at `task` in `list`, stopping its timer and adding
+/// the next one if it repeats. Tasks blocked by unfinished ones are left
+/// alone and `false` returned.
+pub fn complete_task(list: &mut TaskList, task: usize, now: DateTime<Utc>) -> bool {
+    if list.is_blocked(&list.tasks[task]) {
+        return false;
+    }
+    let task = &mut list.tasks[task];
+    task.stop_timer();
+    task.completed_at = Some(now);
+    task.status = Status::Done;
+    task.updated_at = Some(now);
+    spawn_recurring(list, now);
+    true
+}
+
+/// Takes the task at `task` out of `list`, dropping it from the others'
+/// dependencies, and returns it ready for the trash.
+pub fn remove_task(list: &mut TaskList, task: usize) -> Trashed {
+    let removed = list.tasks.remove(task);
+    list.prune_dependencies();
+    Trashed {
+        list_id: list.id,
+        position: task,
+        task: removed,
+    }
+}
@@ -1,84 +1,712 @@
+use chrono::prelude::{DateTime, Utc};
+use chrono::Duration;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use chrono::prelude::{DateTime, Local};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
 use thiserror::Error;
-use std::{fs, io};
+
+use crate::recurrence::Recurrence;
+use crate::store::open_store;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TaskList {
     pub id: usize,
     pub name: String,
-    pub tasks: Box<Vec<Task>>,
+    pub tasks: Vec<Task>,
+    /// Marks the list in the sidebar, `None` for plain white
+    #[serde(default)]
+    pub color: Option<ListColor>,
+    /// Shown above the other lists in the sidebar
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ListColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl ListColor {
+    /// Every color, in the order they're cycled through.
+    pub const ALL: [ListColor; 6] = [
+        ListColor::Red,
+        ListColor::Green,
+        ListColor::Yellow,
+        ListColor::Blue,
+        ListColor::Magenta,
+        ListColor::Cyan,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ListColor::Red => "red",
+            ListColor::Green => "green",
+            ListColor::Yellow => "yellow",
+            ListColor::Blue => "blue",
+            ListColor::Magenta => "magenta",
+            ListColor::Cyan => "cyan",
+        }
+    }
+
+    /// The color after `color`, going back to none after the last one.
+    pub fn cycle(color: Option<ListColor>) -> Option<ListColor> {
+        match color {
+            None => Some(ListColor::ALL[0]),
+            Some(color) => {
+                let next = ListColor::ALL.iter().position(|&c| c == color).unwrap() + 1;
+                ListColor::ALL.get(next).copied()
+            }
+        }
+    }
+}
+
+/// Where a task stands. `Done` goes together with `completed_at` being set.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Status {
+    #[default]
+    Todo,
+    InProgress,
+    /// Waiting on someone else
+    Waiting,
+    Done,
+}
+
+impl Status {
+    /// Every status, in the order tasks go through them.
+    pub const ALL: [Status; 4] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Waiting,
+        Status::Done,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Todo => "Todo",
+            Status::InProgress => "In Progress",
+            Status::Waiting => "Waiting",
+            Status::Done => "Done",
+        }
+    }
+}
+
+/// How much a task matters, ordered from least to most.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+
+    /// The next priority up, wrapping from Urgent back to Low.
+    pub fn next(self) -> Priority {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Urgent,
+            Priority::Urgent => Priority::Low,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Task {
     pub id: usize,
     pub name: String,
-    pub tags: Box<Vec<String>>,
-    pub start_date: DateTime<Local>,
-    pub due_date: DateTime<Local>,
+    pub tags: Vec<String>,
+    /// Dates are stored in UTC and only converted to local time for display
+    pub start_date: DateTime<Utc>,
+    pub due_date: DateTime<Utc>,
+    /// Seconds tracked against this task, excluding a running timer
+    #[serde(default)]
+    pub time_spent: i64,
+    /// When the running timer was started, if there is one
+    #[serde(default)]
+    pub running_since: Option<DateTime<Utc>>,
+    /// Estimated effort in seconds
+    #[serde(default)]
+    pub estimate: Option<i64>,
+    /// Ids of tasks in the same list that have to be finished first
+    #[serde(default)]
+    pub depends_on: Vec<usize>,
+    /// When the task was checked off, `None` while it's still to do
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub status: Status,
+    /// How long before the due date to remind, in seconds, instead of the
+    /// configured default
+    #[serde(default)]
+    pub reminder_lead: Option<i64>,
+    /// The due date the last reminder fired for
+    #[serde(default)]
+    pub reminded_for: Option<DateTime<Utc>>,
+    /// When the task was last edited, `None` if it hasn't been since it was
+    /// added
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Progress notes, oldest first. They're only ever added to.
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Smaller steps checked off one by one, in the order they were added
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+    /// How often the task comes back once it's done
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Subtask {
+    pub name: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub at: DateTime<Utc>,
+}
+
+impl TaskList {
+    /// An empty list with no color.
+    pub fn new(id: usize, name: String) -> TaskList {
+        TaskList {
+            id,
+            name,
+            tasks: Vec::new(),
+            color: None,
+            pinned: false,
+        }
+    }
+
+    pub fn task_by_id(&self, id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    /// Whether any of the task's dependencies is still to do.
+    pub fn is_blocked(&self, task: &Task) -> bool {
+        task.depends_on
+            .iter()
+            .any(|&id| self.task_by_id(id).is_some_and(|dep| !dep.is_completed()))
+    }
+
+    /// Whether `to` can be reached from `from` by following dependencies.
+    pub fn depends_transitively(&self, from: usize, to: usize) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            if seen.insert(id) {
+                if let Some(task) = self.task_by_id(id) {
+                    stack.extend(&task.depends_on);
+                }
+            }
+        }
+        false
+    }
+
+    /// Drops dependencies on tasks that are no longer in the list.
+    pub fn prune_dependencies(&mut self) {
+        let ids: HashSet<usize> = self.tasks.iter().map(|task| task.id).collect();
+        for task in &mut self.tasks {
+            task.depends_on.retain(|id| ids.contains(id));
+        }
+    }
 }
 
-const DB_PATH: &str = "./data/db.json";
+impl Task {
+    /// A task with no tags that starts now.
+    pub fn new(id: usize, name: String, due_date: DateTime<Utc>) -> Task {
+        Task {
+            id,
+            name,
+            tags: Vec::new(),
+            start_date: Utc::now(),
+            due_date,
+            time_spent: 0,
+            running_since: None,
+            estimate: None,
+            depends_on: Vec::new(),
+            completed_at: None,
+            status: Status::Todo,
+            reminder_lead: None,
+            reminded_for: None,
+            updated_at: None,
+            comments: Vec::new(),
+            priority: Priority::default(),
+            subtasks: Vec::new(),
+            recurrence: None,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// When the task was last edited, or added if it never was.
+    pub fn last_edited(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.start_date)
+    }
+
+    /// How many subtasks are done and how many there are, `None` if there
+    /// aren't any.
+    pub fn subtask_progress(&self) -> Option<(usize, usize)> {
+        if self.subtasks.is_empty() {
+            return None;
+        }
+        let done = self.subtasks.iter().filter(|subtask| subtask.done).count();
+        Some((done, self.subtasks.len()))
+    }
+
+    /// Whether the dates are the wrong way around, as can happen in a
+    /// hand-edited db.
+    pub fn due_before_start(&self) -> bool {
+        self.due_date < self.start_date
+    }
+
+    /// Total tracked time, including the running timer.
+    pub fn elapsed(&self) -> Duration {
+        let running = self
+            .running_since
+            .map_or_else(Duration::zero, |since| Utc::now() - since);
+        Duration::seconds(self.time_spent) + running
+    }
+
+    pub fn start_timer(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Utc::now());
+        }
+    }
+
+    pub fn stop_timer(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.time_spent += (Utc::now() - since).num_seconds();
+        }
+    }
+}
+
+pub const DB_FILE: &str = "db.json";
+pub const SQLITE_FILE: &str = "db.sqlite";
+pub const YAML_FILE: &str = "db.yaml";
+pub const DEMO_FILE: &str = "demo.json";
+pub const SPLIT_DIR: &str = "lists";
+
+/// Where data was kept before it moved to the user's data directory, still
+/// used if it holds a db and the new directory doesn't exist yet
+const OLD_DATA_DIR: &str = "./data";
+
+/// `tbg` in the user's data directory: `$XDG_DATA_HOME`, `~/.local/share`,
+/// or `%APPDATA%` on Windows.
+pub fn data_dir() -> PathBuf {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    let dir = var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+        .map(|dir| dir.join("tbg"));
+    let old = Path::new(OLD_DATA_DIR);
+    let has_old_db = [DB_FILE, SQLITE_FILE, YAML_FILE, SPLIT_DIR]
+        .iter()
+        .any(|file| old.join(file).exists());
+    match dir {
+        Some(dir) if dir.exists() || !has_old_db => dir,
+        _ => old.to_path_buf(),
+    }
+}
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("error reading the DB file: {0}")]
     ReadDBError(#[from] io::Error),
     #[error("error parsing the DB file: {0}")]
     ParseDBError(#[from] serde_json::Error),
+    #[error("error parsing the YAML DB file: {0}")]
+    ParseYamlError(#[from] serde_yaml::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("error accessing the SQLite DB: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[cfg(not(feature = "sqlite"))]
+    #[error("SQLite support requires building with the `sqlite` feature")]
+    NoSqliteError,
+    #[cfg(any(feature = "caldav", feature = "todoist"))]
+    #[error("error syncing: {0}")]
+    SyncError(String),
+    #[cfg(not(feature = "caldav"))]
+    #[error("syncing requires building with the `caldav` feature")]
+    NoCaldavError,
+    #[cfg(not(feature = "todoist"))]
+    #[error("syncing with Todoist requires building with the `todoist` feature")]
+    NoTodoistError,
+}
+
+/// Checks that the backend for `path` was compiled in.
+pub fn check_backend(path: &Path) -> Result<(), Error> {
+    open_store(path).map(|_| ())
 }
 
-pub fn read_db() -> Vec<TaskList> {
-    if let Ok(db_content) = fs::read_to_string(DB_PATH) {
-        if let Ok(parsed) = serde_json::from_str::<Vec<TaskList>>(&db_content) {
-            return parsed;
+/// Ids identify tasks within a list, so a hand-edited file with repeats gets
+/// the later copies renumbered. Returns whether anything changed.
+fn renumber_duplicate_ids(lists: &mut [TaskList]) -> bool {
+    let mut changed = false;
+    for list in lists {
+        let mut next = list.tasks.iter().map(|task| task.id + 1).max().unwrap_or(0);
+        let mut seen = HashSet::new();
+        for task in &mut list.tasks {
+            if !seen.insert(task.id) {
+                warn!(
+                    "duplicate task id {} in {}, renumbering {} to {}",
+                    task.id, list.name, task.name, next
+                );
+                task.id = next;
+                next += 1;
+                changed = true;
+            }
         }
     }
-    // Default list
-    let default = vec![
+    changed
+}
+
+/// Writes `contents` to a temporary file beside `path` and renames it over
+/// `path`, so a crash halfway through leaves the old file whole.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+pub fn write_db(path: &Path, lists: &[TaskList]) -> Result<(), Error> {
+    debug!("saving {} lists to {}", lists.len(), path.display());
+    // The data directory doesn't exist before the first save
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    open_store(path)?.save(lists)
+}
+
+/// Adds `list` to the db at `path`, creating it if there isn't one yet. The
+/// list gets a new id if the destination already uses its id.
+pub fn append_list(path: &Path, mut list: TaskList) -> Result<(), Error> {
+    let mut lists = if path.exists() {
+        open_store(path)?.load()?
+    } else {
+        Vec::new()
+    };
+    if lists.iter().any(|l| l.id == list.id) {
+        list.id = lists.iter().map(|l| l.id + 1).max().unwrap_or(0);
+    }
+    info!("moving list {} to {}", list.name, path.display());
+    lists.push(list);
+    write_db(path, &lists)
+}
+
+/// Saves only the list with id `id`, for changes that didn't touch the others.
+pub fn write_list(path: &Path, lists: &[TaskList], id: usize) -> Result<(), Error> {
+    let list = match lists.iter().find(|list| list.id == id) {
+        Some(list) => list,
+        None => return Ok(()),
+    };
+    debug!("saving list {} to {}", list.name, path.display());
+    open_store(path)?.save_list(lists, list)
+}
+
+/// Loads the lists at `path`, writing a single empty list there on the
+/// first run. A file that exists but won't load is left alone and its error
+/// returned, rather than overwritten.
+pub fn read_db(path: &Path) -> Result<Vec<TaskList>, Error> {
+    if !path.exists() {
+        info!("no db at {}, starting with an empty list", path.display());
+        // First run, start with one empty list rather than made up tasks
+        let default = vec![TaskList::new(0, "Tasks".to_string())];
+        write_db(path, &default)?;
+        return Ok(default);
+    }
+    let mut parsed = open_store(path)?.load()?;
+    info!("loaded {} lists from {}", parsed.len(), path.display());
+    // Files from before statuses only have the completion date
+    for task in parsed.iter_mut().flat_map(|list| list.tasks.iter_mut()) {
+        if task.is_completed() {
+            task.status = Status::Done;
+        }
+    }
+    if renumber_duplicate_ids(&mut parsed) {
+        if let Err(err) = write_db(path, &parsed) {
+            warn!(
+                "couldn't save renumbered ids to {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+    Ok(parsed)
+}
+
+/// Sample lists for trying tbg out with `--demo`.
+pub fn demo_lists() -> Vec<TaskList> {
+    // One timestamp so no task ends up due a moment before it starts
+    let now = Utc::now();
+    vec![
         TaskList {
             id: 0,
             name: "Personal".to_string(),
-            tasks: Box::new(vec![
+            tasks: vec![
                 Task {
                     id: 0,
                     name: "Clean up your room".to_string(),
-                    tags: Box::new(vec!["JP".to_string()]),
-                    due_date: Local::now(),
-                    start_date: Local::now(),
+                    tags: vec!["JP".to_string()],
+                    due_date: now,
+                    start_date: now,
+                    time_spent: 0,
+                    running_since: None,
+                    estimate: None,
+                    depends_on: Vec::new(),
+                    completed_at: None,
+                    status: Status::Todo,
+                    reminder_lead: None,
+                    reminded_for: None,
+                    updated_at: None,
+                    comments: Vec::new(),
+                    priority: Priority::default(),
+                    subtasks: Vec::new(),
+                    recurrence: None,
                 },
                 Task {
                     id: 1,
                     name: "Watch ThePrimeagen".to_string(),
-                    tags: Box::new(vec!["rust".to_string()]),
-                    due_date: Local::now(),
-                    start_date: Local::now(),
+                    tags: vec!["rust".to_string()],
+                    due_date: now,
+                    start_date: now,
+                    time_spent: 0,
+                    running_since: None,
+                    estimate: None,
+                    depends_on: Vec::new(),
+                    completed_at: None,
+                    status: Status::Todo,
+                    reminder_lead: None,
+                    reminded_for: None,
+                    updated_at: None,
+                    comments: Vec::new(),
+                    priority: Priority::default(),
+                    subtasks: Vec::new(),
+                    recurrence: None,
                 },
-            ]),
+            ],
+            color: None,
+            pinned: false,
         },
         TaskList {
             id: 1,
             name: "School".to_string(),
-            tasks: Box::new(vec![
+            tasks: vec![
                 Task {
                     id: 0,
                     name: "Math HW".to_string(),
-                    tags: Box::new(vec!["MATH".to_string()]),
-                    due_date: Local::now(),
-                    start_date: Local::now(),
+                    tags: vec!["MATH".to_string()],
+                    due_date: now,
+                    start_date: now,
+                    time_spent: 0,
+                    running_since: None,
+                    estimate: None,
+                    depends_on: Vec::new(),
+                    completed_at: None,
+                    status: Status::Todo,
+                    reminder_lead: None,
+                    reminded_for: None,
+                    updated_at: None,
+                    comments: Vec::new(),
+                    priority: Priority::default(),
+                    subtasks: Vec::new(),
+                    recurrence: None,
                 },
                 Task {
                     id: 1,
                     name: "Smart Book".to_string(),
-                    tags: Box::new(vec!["2070".to_string()]),
-                    due_date: Local::now(),
-                    start_date: Local::now(),
+                    tags: vec!["2070".to_string()],
+                    due_date: now,
+                    start_date: now,
+                    time_spent: 0,
+                    running_since: None,
+                    estimate: None,
+                    depends_on: Vec::new(),
+                    completed_at: None,
+                    status: Status::Todo,
+                    reminder_lead: None,
+                    reminded_for: None,
+                    updated_at: None,
+                    comments: Vec::new(),
+                    priority: Priority::default(),
+                    subtasks: Vec::new(),
+                    recurrence: None,
                 },
-            ]),
+            ],
+            color: None,
+            pinned: false,
         },
-    ];
-    let db_content = serde_json::to_string(&default).unwrap();
-    fs::write(DB_PATH, db_content).unwrap();
-    default
+    ]
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::config::Config;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn task(id: usize, name: &str, depends_on: &[usize]) -> Task {
+        Task {
+            depends_on: depends_on.to_vec(),
+            ..Task::new(id, name.to_string(), Utc::now())
+        }
+    }
+
+    /// A db path of its own in the temp directory, for tests that save.
+    pub(crate) fn scratch_db() -> PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        env::temp_dir()
+            .join(format!(
+                "tbg-test-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            ))
+            .join("db.json")
+    }
+
+    #[test]
+    fn missing_db_starts_with_an_empty_list() {
+        let path = scratch_db();
+        let lists = read_db(&path).unwrap();
+        assert_eq!(lists.len(), 1);
+        assert!(lists[0].tasks.is_empty());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn corrupt_db_is_left_alone_and_fails_startup() {
+        let path = scratch_db();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[{\"id\": 0, \"name\": \"Tasks\", \"tasks\": [").unwrap();
+        assert!(matches!(read_db(&path), Err(Error::ParseDBError(_))));
+        assert!(App::new(Config::default(), path.clone()).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[{\"id\": 0, \"name\": \"Tasks\", \"tasks\": ["
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_in_a_list_are_renumbered() {
+        let mut lists = vec![TaskList {
+            tasks: vec![
+                task(0, "a", &[]),
+                task(1, "b", &[0]),
+                task(0, "c", &[1]),
+                task(1, "d", &[0, 1]),
+            ],
+            ..TaskList::new(0, "One".to_string())
+        }];
+        assert!(renumber_duplicate_ids(&mut lists));
+        let tasks = &lists[0].tasks;
+        let ids: Vec<usize> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [0, 1, 2, 3]);
+        // The first of each id keeps it, so dependencies still point at a task
+        for task in tasks {
+            for id in &task.depends_on {
+                assert!(lists[0].task_by_id(*id).is_some());
+                assert_ne!(*id, task.id, "{} depends on itself", task.name);
+            }
+        }
+        assert_eq!(lists[0].task_by_id(0).unwrap().name, "a");
+        assert_eq!(lists[0].task_by_id(1).unwrap().name, "b");
+        assert!(!renumber_duplicate_ids(&mut lists));
+    }
+
+    #[test]
+    fn the_same_id_in_different_lists_is_left_alone() {
+        let mut lists = vec![
+            TaskList {
+                tasks: vec![task(0, "a", &[]), task(1, "b", &[0])],
+                ..TaskList::new(0, "One".to_string())
+            },
+            TaskList {
+                tasks: vec![task(0, "c", &[]), task(1, "d", &[0])],
+                ..TaskList::new(1, "Two".to_string())
+            },
+        ];
+        assert!(!renumber_duplicate_ids(&mut lists));
+        assert_eq!(lists[1].task_by_id(0).unwrap().name, "c");
+        assert_eq!(lists[1].tasks[1].depends_on, [0]);
+    }
+
+    #[test]
+    fn renumbered_ids_are_saved_on_load() {
+        let path = scratch_db();
+        let list = TaskList {
+            tasks: vec![task(3, "a", &[]), task(3, "b", &[3])],
+            ..TaskList::new(0, "One".to_string())
+        };
+        write_db(&path, &[list]).unwrap();
+        let lists = read_db(&path).unwrap();
+        assert_eq!(lists[0].tasks[1].id, 4);
+        assert_eq!(lists[0].tasks[1].depends_on, [3]);
+        let saved = fs::read_to_string(&path).unwrap();
+        let saved: Vec<TaskList> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(saved[0].tasks[1].id, 4);
+    }
+
+    #[test]
+    fn dates_with_local_offsets_are_migrated_to_utc() {
+        let path = scratch_db();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let old = r#"[{"id": 0, "name": "Tasks", "tasks": [{
+            "id": 0,
+            "name": "Old",
+            "tags": ["home"],
+            "start_date": "2021-03-01T09:00:00+02:00",
+            "due_date": "2021-03-05T17:30:00-05:00"
+        }]}]"#;
+        fs::write(&path, old).unwrap();
+        let lists = read_db(&path).unwrap();
+        let task = &lists[0].tasks[0];
+        assert_eq!(task.start_date.to_rfc3339(), "2021-03-01T07:00:00+00:00");
+        assert_eq!(task.due_date.to_rfc3339(), "2021-03-05T22:30:00+00:00");
+        assert_eq!(task.tags, ["home"]);
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"2021-03-01T07:00:00Z\""));
+        assert!(saved.contains("\"2021-03-05T22:30:00Z\""));
+        assert!(!saved.contains("+02:00") && !saved.contains("-05:00"));
+    }
 }
@@ -0,0 +1,78 @@
+/// Parses durations like "90m", "2h", "1w" or "1h30m". A bare number is minutes.
+pub fn parse_duration(input: &str) -> Option<chrono::Duration> {
+    let input = input.trim();
+    if let Ok(minutes) = input.parse::<i64>() {
+        return Some(chrono::Duration::minutes(minutes));
+    }
+    let mut total = chrono::Duration::zero();
+    let mut number = String::new();
+    for c in input.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'w' | 'd' | 'h' | 'm' | 's' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'w' => chrono::Duration::weeks(n),
+                    'd' => chrono::Duration::days(n),
+                    'h' => chrono::Duration::hours(n),
+                    'm' => chrono::Duration::minutes(n),
+                    _ => chrono::Duration::seconds(n),
+                };
+            }
+            ' ' => {}
+            _ => return None,
+        }
+    }
+    if number.is_empty() && total > chrono::Duration::zero() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Describes a duration in its largest whole unit, like "5 days" or "3 hours".
+pub fn humanize_duration(duration: chrono::Duration) -> String {
+    let (count, unit) = if duration.num_days().abs() > 0 {
+        (duration.num_days(), "day")
+    } else if duration.num_hours().abs() > 0 {
+        (duration.num_hours(), "hour")
+    } else {
+        (duration.num_minutes(), "minute")
+    };
+    let plural = if count.abs() == 1 { "" } else { "s" };
+    format!("{} {}{}", count, unit, plural)
+}
+
+/// Describes the time until `left` runs out, like "in 3 hours" or "2 days
+/// ago".
+pub fn countdown(left: chrono::Duration) -> String {
+    if left.num_minutes() == 0 {
+        "now".to_string()
+    } else if left > chrono::Duration::zero() {
+        format!("in {}", humanize_duration(left))
+    } else {
+        format!("{} ago", humanize_duration(-left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn countdown_reads_time_left_or_past() {
+        assert_eq!(
+            countdown(Duration::hours(3) + Duration::seconds(5)),
+            "in 3 hours"
+        );
+        assert_eq!(countdown(-Duration::days(2)), "2 days ago");
+        assert_eq!(countdown(Duration::seconds(20)), "now");
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::prelude::{DateTime, Utc};
+use std::io::Write;
+use std::path::Path;
+use std::{fs, io};
+
+use crate::db::{Error, Priority, Status, Task, TaskList};
+use crate::recurrence::Recurrence;
+
+/// Writes an export to `path`, or to stdout for `-` so it can be piped into
+/// another app.
+pub fn write_export(path: &Path, contents: &str) -> io::Result<()> {
+    if path == Path::new("-") {
+        io::stdout().write_all(contents.as_bytes())
+    } else {
+        fs::write(path, contents)
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or newline.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn export_csv(lists: &[TaskList], path: &Path) -> Result<(), Error> {
+    let mut csv = String::from("list,name,tags,start_date,due_date\n");
+    for list in lists {
+        for task in &list.tasks {
+            let fields = [
+                list.name.clone(),
+                task.name.clone(),
+                task.tags.join(" "),
+                task.start_date.to_rfc3339(),
+                task.due_date.to_rfc3339(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+pub fn export_yaml(lists: &[TaskList], path: &Path) -> Result<(), Error> {
+    fs::write(path, serde_yaml::to_string(lists)?)?;
+    Ok(())
+}
+
+/// Where the export key writes the calendar, beside the db
+pub const ICS_FILE: &str = "tasks.ics";
+
+/// Escapes text for an iCalendar property value.
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn ics_date(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Adds a content line, folded so no line is longer than 75 bytes.
+fn push_ics_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// iCalendar priorities run from 1 for the highest to 9 for the lowest.
+fn ics_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Urgent => 1,
+        Priority::High => 3,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+fn ics_status(status: Status) -> &'static str {
+    match status {
+        Status::Todo | Status::Waiting => "NEEDS-ACTION",
+        Status::InProgress => "IN-PROCESS",
+        Status::Done => "COMPLETED",
+    }
+}
+
+fn ics_rule(recurrence: Recurrence) -> String {
+    match recurrence {
+        Recurrence::Daily => "FREQ=DAILY".to_string(),
+        Recurrence::Weekly => "FREQ=WEEKLY".to_string(),
+        Recurrence::Monthly => "FREQ=MONTHLY".to_string(),
+        Recurrence::Every(seconds) => {
+            let (freq, unit) = [("DAILY", 86400), ("HOURLY", 3600), ("MINUTELY", 60)]
+                .iter()
+                .copied()
+                .find(|&(_, unit)| seconds % unit == 0)
+                .unwrap_or(("SECONDLY", 1));
+            format!("FREQ={};INTERVAL={}", freq, seconds / unit)
+        }
+    }
+}
+
+/// One task as a to-do, with `categories` ahead of its tags.
+pub fn vtodo_lines(uid: &str, task: &Task, categories: &[&str]) -> Vec<String> {
+    let categories: Vec<String> = categories
+        .iter()
+        .copied()
+        .chain(task.tags.iter().map(String::as_str))
+        .map(ics_text)
+        .collect();
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", ics_date(Utc::now())),
+        format!("SUMMARY:{}", ics_text(&task.name)),
+        format!("DTSTART:{}", ics_date(task.start_date)),
+        format!("DUE:{}", ics_date(task.due_date)),
+        format!("STATUS:{}", ics_status(task.status)),
+        format!("PRIORITY:{}", ics_priority(task.priority)),
+    ];
+    if !categories.is_empty() {
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    if let Some(completed) = task.completed_at {
+        lines.push(format!("COMPLETED:{}", ics_date(completed)));
+    }
+    if let Some(updated) = task.updated_at {
+        lines.push(format!("LAST-MODIFIED:{}", ics_date(updated)));
+    }
+    if let Some(recurrence) = task.recurrence {
+        lines.push(format!("RRULE:{}", ics_rule(recurrence)));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+/// A calendar holding `todos`, folded and with the CRLF line endings
+/// iCalendar asks for.
+pub fn ics_calendar(todos: &[Vec<String>]) -> String {
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//tbg//Tasks But Good//EN",
+    ] {
+        push_ics_line(&mut ics, line);
+    }
+    for line in todos.iter().flatten() {
+        push_ics_line(&mut ics, line);
+    }
+    push_ics_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+/// Writes every task as a to-do in an iCalendar file, with the list it's in
+/// and its tags as categories.
+pub fn export_ics(lists: &[TaskList], path: &Path) -> Result<(), Error> {
+    let todos: Vec<Vec<String>> = lists
+        .iter()
+        .flat_map(|list| {
+            list.tasks.iter().map(move |task| {
+                let uid = format!("tbg-{}-{}", list.id, task.id);
+                vtodo_lines(&uid, task, &[&list.name])
+            })
+        })
+        .collect();
+    write_export(path, &ics_calendar(&todos))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::scratch_db;
+
+    #[test]
+    fn ics_export_writes_tasks_as_todos() {
+        let mut task = Task::new(0, "Water plants, then mop".to_string(), Utc::now());
+        task.tags = vec!["chores".to_string()];
+        task.recurrence = Some(Recurrence::Every(2 * 86400));
+        let home = TaskList {
+            tasks: vec![task],
+            ..TaskList::new(0, "Home".to_string())
+        };
+        let path = scratch_db().with_file_name("tasks.ics");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        export_ics(&[home], &path).unwrap();
+        let ics = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = ics.split("\r\n").collect();
+        assert_eq!(lines[0], "BEGIN:VCALENDAR");
+        assert!(lines.contains(&"BEGIN:VTODO"));
+        assert!(lines.contains(&"UID:tbg-0-0"));
+        assert!(lines.contains(&"SUMMARY:Water plants\\, then mop"));
+        assert!(lines.contains(&"CATEGORIES:Home,chores"));
+        assert!(lines.contains(&"RRULE:FREQ=DAILY;INTERVAL=2"));
+        assert!(lines.iter().all(|line| line.len() <= 75));
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::prelude::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono::Duration;
+
+use crate::app::TaskRef;
+use crate::db::Task;
+use crate::duration::parse_duration;
+use crate::natural::parse_natural_date;
+
+/// Labels of the form's fields, in the order Tab goes through them.
+pub const FORM_FIELDS: [&str; 4] = ["Name", "Tags", "Start", "Due"];
+
+/// The task form opened with `a` for a new task or `e` to edit one, one
+/// line of input per field.
+#[derive(Default)]
+pub struct TaskForm {
+    pub fields: [String; FORM_FIELDS.len()],
+    /// Index of the field being typed into
+    pub focused: usize,
+    /// The task being edited, `None` for a new one
+    pub editing: Option<TaskRef>,
+    /// Start and due date of the edited task as shown in the form, so dates
+    /// left alone keep their seconds
+    kept: [Option<(String, DateTime<Utc>)>; 2],
+}
+
+/// A task as filled in on the form.
+pub struct NewTask {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub start: DateTime<Utc>,
+    pub due: DateTime<Utc>,
+}
+
+impl TaskForm {
+    /// A form filled in with `task`'s current values.
+    pub fn for_task(task_ref: TaskRef, task: &Task, date_format: &str) -> TaskForm {
+        let shown =
+            |date: DateTime<Utc>| date.with_timezone(&Local).format(date_format).to_string();
+        let start = shown(task.start_date);
+        let due = shown(task.due_date);
+        TaskForm {
+            fields: [
+                task.name.clone(),
+                task.tags.join(", "),
+                start.clone(),
+                due.clone(),
+            ],
+            focused: 0,
+            editing: Some(task_ref),
+            kept: [Some((start, task.start_date)), Some((due, task.due_date))],
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.focused = (self.focused + 1) % FORM_FIELDS.len();
+    }
+
+    pub fn previous_field(&mut self) {
+        self.focused = (self.focused + FORM_FIELDS.len() - 1) % FORM_FIELDS.len();
+    }
+
+    pub fn input(&mut self) -> &mut String {
+        &mut self.fields[self.focused]
+    }
+
+    /// Reads the fields, describing the first one that doesn't make sense.
+    /// Tasks start now and are due `default_due` later unless given dates.
+    pub fn parse(&self, date_format: &str, default_due: Duration) -> Result<NewTask, String> {
+        let [name, tags, start, due] = &self.fields;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("A task needs a name".to_string());
+        }
+        let kept = |field: usize, input: &str| {
+            self.kept[field]
+                .as_ref()
+                .filter(|(shown, _)| shown == input.trim())
+                .map(|&(_, date)| date)
+        };
+        let now = Utc::now();
+        let start = kept(0, start)
+            .or_else(|| parse_date(start, date_format, now))
+            .ok_or_else(|| format!("Can't read the start date {}", start.trim()))?;
+        let due = kept(1, due)
+            .or_else(|| parse_date(due, date_format, start + default_due))
+            .ok_or_else(|| format!("Can't read the due date {}", due.trim()))?;
+        if due < start {
+            return Err("The due date can't be before the start date".to_string());
+        }
+        Ok(NewTask {
+            name: name.to_string(),
+            tags: tags
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|tag| tag.trim_start_matches('#'))
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            start,
+            due,
+        })
+    }
+}
+
+/// Reads a date typed into the form: empty for `default`, a duration like
+/// "2d" for that long from now, a local date in `date_format`,
+/// "YYYY-MM-DD HH:MM" or "YYYY-MM-DD", or words like "tomorrow 5pm".
+pub fn parse_date(input: &str, date_format: &str, default: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(default);
+    }
+    if let Some(duration) = parse_duration(input) {
+        return Some(Utc::now() + duration);
+    }
+    let naive = [date_format, "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|day| day.and_hms_opt(0, 0, 0))
+        });
+    let date = match naive {
+        Some(naive) => Local.from_local_datetime(&naive).earliest()?,
+        None => parse_natural_date(input, Local::now())?,
+    };
+    Some(date.with_timezone(&Utc))
+}
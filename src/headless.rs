@@ -0,0 +1,110 @@
+use chrono::prelude::Local;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::app::TagFilter;
+use crate::bucket::Bucket;
+use crate::core::TaskId;
+use crate::db::{Error, Task, TaskList};
+
+/// Filters and format for `tbg list`, which prints tasks instead of starting
+/// the UI.
+#[derive(Default)]
+pub struct ListOptions {
+    pub due: Option<Bucket>,
+    pub tag: Option<String>,
+    pub list_name: Option<String>,
+    pub json: bool,
+    /// Whether lines start with the task's id, for `done` and `rm`
+    pub ids: bool,
+}
+
+#[derive(Serialize)]
+struct ListedTask<'a> {
+    list: &'a str,
+    list_id: usize,
+    #[serde(flatten)]
+    task: &'a Task,
+}
+
+/// Prints the tasks matching `options`, one tab separated line each or as a
+/// JSON array.
+pub fn print_tasks(
+    lists: &[TaskList],
+    options: &ListOptions,
+    date_format: &str,
+) -> Result<(), Error> {
+    let now = Local::now();
+    let tag_filter = options.tag.as_ref().map(|tag| TagFilter {
+        tags: vec![tag.clone()],
+        all: false,
+    });
+    let listed: Vec<ListedTask> = lists
+        .iter()
+        .filter(|list| {
+            options
+                .list_name
+                .as_ref()
+                .is_none_or(|name| list.name.eq_ignore_ascii_case(name))
+        })
+        .flat_map(|list| {
+            list.tasks.iter().map(move |task| ListedTask {
+                list: &list.name,
+                list_id: list.id,
+                task,
+            })
+        })
+        .filter(|listed| {
+            options.due.is_none_or(|due| {
+                !listed.task.is_completed() && Bucket::of(listed.task.due_date, now) == due
+            })
+        })
+        .filter(|listed| {
+            tag_filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(listed.task))
+        })
+        .collect();
+
+    let mut output = String::new();
+    if options.json {
+        output.push_str(&serde_json::to_string_pretty(&listed)?);
+        output.push('\n');
+    } else {
+        for ListedTask {
+            list,
+            list_id,
+            task,
+        } in listed
+        {
+            if options.ids {
+                let id = TaskId {
+                    list: list_id,
+                    task: task.id,
+                };
+                output.push_str(&format!("{}\t", id));
+            }
+            output.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                list,
+                task.name,
+                task.tags.join(","),
+                task.due_date.with_timezone(&Local).format(date_format)
+            ));
+        }
+    }
+    print(&output)
+}
+
+/// Prints every list with all of its tasks as JSON, for `dump`.
+pub fn dump_db(lists: &[TaskList]) -> Result<(), Error> {
+    print(&(serde_json::to_string_pretty(lists)? + "\n"))
+}
+
+fn print(output: &str) -> Result<(), Error> {
+    match io::stdout().write_all(output.as_bytes()) {
+        // Piping into something like `head` shouldn't be an error
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
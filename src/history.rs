@@ -0,0 +1,54 @@
+use crate::db::TaskList;
+use crate::trash::Trashed;
+
+/// How many changes can be undone
+pub const HISTORY_LIMIT: usize = 100;
+
+/// The tasks and the trash as they were at one point, together so undoing
+/// a delete also takes the task back out of the trash.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub tasklists: Vec<TaskList>,
+    pub trash: Vec<Trashed>,
+}
+
+/// Snapshots to go back and forth between with undo and redo. Only kept in
+/// memory, so history starts over with every run.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    /// Remembers how things were before a change. A new change can't be
+    /// redone past, so it drops what was undone.
+    pub fn record(&mut self, before: Snapshot) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    /// Swaps `current` for the snapshot before the last change, if there is
+    /// one.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// Swaps `current` for the snapshot the last undo left, if there is one.
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+
+    /// Forgets every change, for ones that can't be taken back.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
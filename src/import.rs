@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::{fs, io};
+
+use crate::app::INBOX;
+use crate::core::{add_task, find_or_add_list};
+use crate::db::{Error, Task, TaskList};
+use crate::taskwarrior::parse_taskwarrior;
+use crate::todotxt::parse_todotxt;
+
+/// Formats tasks can be brought in from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImportFormat {
+    Todotxt,
+    Taskwarrior,
+}
+
+impl ImportFormat {
+    pub fn parse(name: &str) -> Option<ImportFormat> {
+        match name {
+            "todotxt" => Some(ImportFormat::Todotxt),
+            "taskwarrior" => Some(ImportFormat::Taskwarrior),
+            _ => None,
+        }
+    }
+}
+
+/// A task read from another app, with the project it belongs to.
+pub struct Imported {
+    pub project: Option<String>,
+    pub task: Task,
+}
+
+/// Adds `tasks` to the list named after their project, or the inbox, making
+/// the lists that don't exist yet. Returns how many lists were made.
+fn merge_tasks(lists: &mut Vec<TaskList>, tasks: Vec<Imported>) -> usize {
+    let mut created = 0;
+    for Imported { project, task } in tasks {
+        let (list, added) = find_or_add_list(lists, project.as_deref().unwrap_or(INBOX));
+        created += added as usize;
+        add_task(&mut lists[list], task);
+    }
+    created
+}
+
+/// Reads the file at `path`, or stdin for `-`, into `lists`. Returns how
+/// many tasks and new lists there were.
+pub fn import_file(
+    lists: &mut Vec<TaskList>,
+    format: ImportFormat,
+    path: &Path,
+) -> Result<(usize, usize), Error> {
+    let content = if path == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(path)?
+    };
+    let tasks = match format {
+        ImportFormat::Todotxt => parse_todotxt(&content),
+        ImportFormat::Taskwarrior => parse_taskwarrior(&content)?,
+    };
+    let count = tasks.len();
+    Ok((count, merge_tasks(lists, tasks)))
+}
@@ -0,0 +1,1407 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{App, Mode, PromptKind};
+use crate::duration::parse_duration;
+use crate::keymap::Action;
+use crate::search::{best_match, Finder};
+use crate::TASK_COLUMNS;
+
+/// Moves a selection one down, stopping at `last` unless `wrap` is set.
+fn step_down(selected: usize, last: usize, wrap: bool) -> usize {
+    if selected < last {
+        selected + 1
+    } else if wrap {
+        0
+    } else {
+        last
+    }
+}
+
+/// Moves a selection one up, stopping at 0 unless `wrap` is set.
+fn step_up(selected: usize, last: usize, wrap: bool) -> usize {
+    if selected > 0 {
+        selected - 1
+    } else if wrap {
+        last
+    } else {
+        0
+    }
+}
+
+/// Applies a key press to the app. This is the whole of the keyboard
+/// handling, kept apart from the terminal so it can be driven in tests.
+/// Presses that change the tasks are recorded so they can be undone.
+pub fn handle_key(app: &mut App, event: KeyEvent) {
+    app.prepare_undo();
+    let revision = app.revision;
+    apply_key(app, event);
+    if app.revision != revision {
+        app.record_undo();
+    }
+}
+
+fn apply_key(app: &mut App, event: KeyEvent) {
+    let list_len = app.sidebar_len() - 1;
+    let selected_entry = app.selected_entry();
+    let visible = app.visible_tasks();
+    let task_len = visible.len().saturating_sub(1);
+    let wrap_around = app.config.wrap_around;
+
+    if event.code == KeyCode::Esc {
+        app.cancel();
+        return;
+    }
+    match app.mode() {
+        Mode::Search => {
+            let query = app.search.as_mut().expect("search is active");
+            match event.code {
+                KeyCode::Enter => {
+                    // Kept for n and N
+                    app.last_search = app.search.take().filter(|query| !query.is_empty());
+                }
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                }
+                _ => {}
+            }
+            if let Some(query) = &app.search {
+                let names = visible.iter().map(|&r| app.task(r).name.as_str());
+                if let Some(best) = best_match(query, names) {
+                    app.task_state.select(Some(best));
+                }
+            }
+        }
+        Mode::Find => {
+            let finder = app.finder.as_mut().expect("finder is open");
+            match event.code {
+                KeyCode::Enter => app.jump_to_found(),
+                KeyCode::Down | KeyCode::Tab => finder.selected += 1,
+                KeyCode::Up | KeyCode::BackTab => {
+                    finder.selected = finder.selected.saturating_sub(1);
+                }
+                KeyCode::Backspace => {
+                    finder.query.pop();
+                    finder.selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    finder.query.push(c);
+                    finder.selected = 0;
+                }
+                _ => {}
+            }
+            if let Some(finder) = &mut app.finder {
+                let found = finder.results(&app.tasklists).len();
+                finder.selected = finder.selected.min(found.saturating_sub(1));
+            }
+        }
+        Mode::Calendar => match (event.code, app.keymap.action(event)) {
+            (_, Some(Action::Calendar)) => {
+                app.calendar = None;
+            }
+            (KeyCode::Enter, _) => {
+                app.pick_calendar_day();
+            }
+            (KeyCode::Left, _) | (_, Some(Action::Left)) => {
+                app.move_calendar(-1);
+            }
+            (KeyCode::Right, _) | (_, Some(Action::Right)) => {
+                app.move_calendar(1);
+            }
+            (KeyCode::Up, _) | (_, Some(Action::Up)) => {
+                app.move_calendar(-7);
+            }
+            (KeyCode::Down, _) | (_, Some(Action::Down)) => {
+                app.move_calendar(7);
+            }
+            (_, Some(Action::ScrollLeft)) => {
+                app.move_calendar_month(-1);
+            }
+            (_, Some(Action::ScrollRight)) => {
+                app.move_calendar_month(1);
+            }
+            _ => {}
+        },
+        Mode::Form => {
+            let form = app.form.as_mut().expect("form is open");
+            match event.code {
+                KeyCode::Enter => {
+                    let result = app.submit_form();
+                    app.report(result);
+                }
+                KeyCode::Tab | KeyCode::Down => form.next_field(),
+                KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+                KeyCode::Backspace => {
+                    form.input().pop();
+                }
+                KeyCode::Char(c) => form.input().push(c),
+                _ => {}
+            }
+        }
+        Mode::Editing => {
+            let prompt = app.prompt.as_mut().expect("prompt is active");
+            match event.code {
+                KeyCode::Enter => {
+                    app.submit_prompt();
+                }
+                KeyCode::Backspace => {
+                    prompt.input.pop();
+                }
+                KeyCode::Char(c) => {
+                    prompt.input.push(c);
+                }
+                KeyCode::Tab => {
+                    app.complete_tag();
+                }
+                _ => {}
+            }
+        }
+        Mode::Confirm => {
+            let yes = matches!(event.code, KeyCode::Char('y'));
+            let acts_on_no = app.confirm.as_ref().is_some_and(|c| c.acts_on_no());
+            if !yes && acts_on_no && event.code != KeyCode::Char('n') {
+                return;
+            }
+            let result = app.answer_confirm(yes);
+            app.report(result);
+        }
+        Mode::Checklist => {
+            // Clicking away can leave no task selected
+            let task = match app.selected_task() {
+                Some(task) => task,
+                None => {
+                    app.checklist = None;
+                    return;
+                }
+            };
+            let row = app.checklist.expect("checklist is open");
+            let last = app.task(task).subtasks.len().saturating_sub(1);
+            match (event.code, app.keymap.action(event)) {
+                (_, Some(Action::Subtasks)) => {
+                    app.checklist = None;
+                }
+                (KeyCode::Down, _) | (_, Some(Action::Down)) => {
+                    app.checklist = Some(step_down(row, last, wrap_around));
+                }
+                (KeyCode::Up, _) | (_, Some(Action::Up)) => {
+                    app.checklist = Some(step_up(row, last, wrap_around));
+                }
+                (_, Some(Action::Add)) => {
+                    app.open_prompt(PromptKind::Subtask);
+                }
+                (_, Some(Action::Complete)) | (_, Some(Action::Mark)) => {
+                    let result = app.toggle_subtask(task, row);
+                    app.report(result);
+                }
+                (_, Some(Action::Delete)) => {
+                    let result = app.delete_subtask(task, row);
+                    app.report(result);
+                }
+                _ => {}
+            }
+        }
+        Mode::TagPicker => {
+            let picker = app.tag_picker.as_mut().expect("tag picker is open");
+            let last = picker.tags.len() - 1;
+            match (event.code, app.keymap.action(event)) {
+                (KeyCode::Enter, _) => app.apply_tag_picker(),
+                (_, Some(Action::Tag)) => {
+                    app.tag_picker = None;
+                }
+                (KeyCode::Down, _) | (_, Some(Action::Down)) => {
+                    picker.selected = step_down(picker.selected, last, wrap_around);
+                }
+                (KeyCode::Up, _) | (_, Some(Action::Up)) => {
+                    picker.selected = step_up(picker.selected, last, wrap_around);
+                }
+                (_, Some(Action::Mark)) => app.toggle_picked_tag(),
+                _ => {}
+            }
+        }
+        Mode::Focus => match (event.code, app.keymap.action(event)) {
+            (_, Some(Action::Focus)) => {
+                app.focus = false;
+            }
+            (_, Some(Action::Complete)) => {
+                if let Some(task) = app.next_action() {
+                    let result = app.toggle_completed(task);
+                    app.report(result);
+                }
+            }
+            (_, Some(Action::ForceQuit)) => {
+                let result = app.quit(false);
+                app.report(result);
+            }
+            (_, Some(Action::Quit)) => {
+                let result = app.quit(true);
+                app.report(result);
+            }
+            _ => {}
+        },
+        Mode::Board => match (event.code, app.keymap.action(event)) {
+            (_, Some(Action::Board)) => {
+                app.board = None;
+            }
+            (KeyCode::Enter, _) => {
+                app.pick_board_task();
+            }
+            (KeyCode::Left, _) | (_, Some(Action::Left)) => {
+                app.move_board(-1, 0);
+            }
+            (KeyCode::Right, _) | (_, Some(Action::Right)) => {
+                app.move_board(1, 0);
+            }
+            (KeyCode::Up, _) | (_, Some(Action::Up)) => {
+                app.move_board(0, -1);
+            }
+            (KeyCode::Down, _) | (_, Some(Action::Down)) => {
+                app.move_board(0, 1);
+            }
+            (_, Some(Action::ScrollLeft)) => {
+                let result = app.shift_board_task(-1);
+                app.report(result);
+            }
+            (_, Some(Action::ScrollRight)) => {
+                let result = app.shift_board_task(1);
+                app.report(result);
+            }
+            (_, Some(Action::ForceQuit)) => {
+                let result = app.quit(false);
+                app.report(result);
+            }
+            (_, Some(Action::Quit)) => {
+                let result = app.quit(true);
+                app.report(result);
+            }
+            _ => {}
+        },
+        Mode::Normal | Mode::Visual => {
+            match (app.keymap.action(event), app.task_state.selected()) {
+                (Some(Action::ForceQuit), _) => {
+                    let result = app.quit(false);
+                    app.report(result);
+                }
+                (Some(Action::Quit), _) => {
+                    let result = app.quit(true);
+                    app.report(result);
+                }
+                (Some(Action::Search), None) => {
+                    app.finder = Some(Finder::default());
+                }
+                (Some(Action::Search), _) => {
+                    app.search = Some(String::new());
+                }
+                (Some(Action::SearchNext), _) => {
+                    app.search_next(true);
+                }
+                (Some(Action::SearchPrevious), _) => {
+                    app.search_next(false);
+                }
+                (Some(Action::Command), _) => {
+                    app.open_prompt(PromptKind::Command);
+                }
+                (Some(Action::GoToList), _) => {
+                    app.open_prompt(PromptKind::GoToList);
+                }
+                (Some(Action::ShrinkLists), _) => {
+                    app.resize_list_panel(false);
+                }
+                (Some(Action::GrowLists), _) => {
+                    app.resize_list_panel(true);
+                }
+                (Some(Action::Collapse), _) => {
+                    app.toggle_collapsed();
+                }
+                (Some(Action::Dense), _) => {
+                    app.dense = !app.dense;
+                }
+                (Some(Action::Hints), _) => {
+                    app.show_hints = !app.show_hints;
+                }
+                (Some(Action::Calendar), _) => {
+                    app.open_calendar();
+                }
+                (Some(Action::Agenda), _) => {
+                    app.toggle_agenda();
+                }
+                (Some(Action::Board), _) => {
+                    app.open_board();
+                }
+                (Some(Action::Add), _) => {
+                    app.open_form();
+                }
+                (Some(Action::Capture), _) => {
+                    app.open_prompt(PromptKind::Capture);
+                }
+                (Some(Action::Restore), _) => {
+                    let result = app.restore_deleted();
+                    app.report(result);
+                }
+                (Some(Action::ReverseSort), _) => {
+                    let result = app.reverse_sort();
+                    app.report(result);
+                }
+                (Some(Action::ScrollLeft), _) => {
+                    app.column_offset = app.column_offset.saturating_sub(1);
+                }
+                (Some(Action::ScrollRight), _) => {
+                    app.column_offset = (app.column_offset + 1).min(TASK_COLUMNS.len() - 2);
+                }
+                (Some(Action::TagFilter), _) => {
+                    app.open_prompt(PromptKind::TagFilter);
+                }
+                (Some(Action::TagMode), _) => {
+                    app.toggle_tag_mode();
+                }
+                (Some(Action::Focus), _) => {
+                    app.focus = true;
+                }
+                (Some(Action::Details), Some(_)) => {
+                    app.show_details = !app.show_details;
+                }
+                // The task panel has a highlighted row
+                (Some(Action::Left), Some(_)) if !app.collapsed => {
+                    app.leave_tasks();
+                }
+                (Some(Action::Down), Some(row)) => {
+                    let next = step_down(row, task_len, wrap_around);
+                    app.task_state.select(Some(next));
+                }
+                (Some(Action::Up), Some(row)) => {
+                    let prev = step_up(row, task_len, wrap_around);
+                    app.task_state.select(Some(prev));
+                }
+                (Some(Action::Visual), Some(_)) => {
+                    app.toggle_visual();
+                }
+                (Some(Action::Mark), Some(row)) if app.marked.is_some() => {
+                    app.toggle_mark(visible[row]);
+                }
+                // Outside visual mode there's nothing to mark, so it checks
+                // the task off
+                (Some(Action::Mark), Some(row)) => {
+                    let result = app.toggle_completed(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::Delete), Some(_)) if app.marked.is_some() => {
+                    app.ask_delete_marked();
+                }
+                (Some(Action::Delete), Some(row)) => {
+                    app.ask_delete_task(visible[row]);
+                }
+                (Some(Action::Delete), None) => {
+                    app.ask_delete_list();
+                }
+                (Some(Action::Move), Some(_)) => {
+                    app.open_prompt(PromptKind::Move);
+                }
+                (Some(Action::Tag), Some(_)) if app.marked.is_some() => {
+                    app.open_prompt(PromptKind::TagMarked);
+                }
+                (Some(Action::Tag), _) => {
+                    app.open_tag_picker();
+                }
+                (Some(Action::Estimate), Some(_)) => {
+                    app.open_prompt(PromptKind::Estimate);
+                }
+                (Some(Action::Edit), Some(row)) => {
+                    app.open_edit_form(visible[row]);
+                }
+                (Some(Action::Comment), Some(_)) => {
+                    app.open_prompt(PromptKind::Comment);
+                }
+                (Some(Action::Reminder), Some(_)) => {
+                    app.open_prompt(PromptKind::Reminder);
+                }
+                (Some(Action::Snooze), Some(row)) => {
+                    let by = parse_duration(&app.config.snooze)
+                        .unwrap_or_else(|| chrono::Duration::days(1));
+                    let result = app.snooze(visible[row], by);
+                    app.report(result);
+                }
+                (Some(Action::SnoozeWeek), Some(row)) => {
+                    let result = app.snooze(visible[row], chrono::Duration::weeks(1));
+                    app.report(result);
+                }
+                (Some(Action::Timer), Some(row)) => {
+                    let result = app.toggle_timer(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::Complete), Some(row)) => {
+                    let result = app.toggle_completed(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::HideCompleted), _) => {
+                    app.toggle_hide_completed();
+                }
+                (Some(Action::Status), Some(row)) => {
+                    let result = app.cycle_status(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::Priority), Some(row)) => {
+                    let result = app.cycle_priority(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::Repeat), Some(_)) => {
+                    app.open_prompt(PromptKind::Repeat);
+                }
+                (Some(Action::Subtasks), Some(_)) => {
+                    app.checklist = Some(0);
+                }
+                (Some(Action::BlockedBy), Some(_)) => {
+                    app.open_prompt(PromptKind::DependsOn);
+                }
+                (Some(Action::Yank), Some(row)) => {
+                    app.yank(visible[row]);
+                }
+                (Some(Action::Duplicate), Some(row)) if app.marked.is_none() => {
+                    let result = app.duplicate_task(visible[row]);
+                    app.report(result);
+                }
+                (Some(Action::MoveToTop), Some(row)) if app.marked.is_none() => {
+                    let result = app.move_task_to_end(visible[row], true);
+                    app.report(result);
+                }
+                (Some(Action::MoveToBottom), Some(row)) if app.marked.is_none() => {
+                    let result = app.move_task_to_end(visible[row], false);
+                    app.report(result);
+                }
+                (Some(Action::MoveDown), Some(row)) if app.marked.is_none() => {
+                    let result = app.move_task(row, false);
+                    app.report(result);
+                }
+                (Some(Action::MoveUp), Some(row)) if app.marked.is_none() => {
+                    let result = app.move_task(row, true);
+                    app.report(result);
+                }
+                // The list panel has focus
+                (Some(Action::Down), None) => {
+                    let next = step_down(selected_entry, list_len, wrap_around);
+                    app.list_state.select(Some(next));
+                }
+                (Some(Action::Up), None) => {
+                    let prev = step_up(selected_entry, list_len, wrap_around);
+                    app.list_state.select(Some(prev));
+                }
+                (Some(Action::Right), None) | (Some(Action::Details), None) => {
+                    app.enter_tasks();
+                }
+                (Some(Action::NewListBelow), None) => {
+                    app.open_prompt(PromptKind::NewListBelow);
+                }
+                (Some(Action::NewListAbove), None) => {
+                    app.open_prompt(PromptKind::NewListAbove);
+                }
+                (Some(Action::MoveDown), None) => {
+                    let result = app.move_list(false);
+                    app.report(result);
+                }
+                (Some(Action::MoveUp), None) => {
+                    let result = app.move_list(true);
+                    app.report(result);
+                }
+                (Some(Action::ListColor), None) => {
+                    let result = app.cycle_list_color();
+                    app.report(result);
+                }
+                (Some(Action::UrgencySort), _) => {
+                    app.toggle_urgency_sort();
+                }
+                (Some(Action::Pin), None) => {
+                    let result = app.toggle_pinned();
+                    app.report(result);
+                }
+                (Some(Action::Undo), _) => {
+                    app.undo(false);
+                }
+                (Some(Action::Redo), _) => {
+                    app.undo(true);
+                }
+                (Some(Action::ExportIcs), _) => {
+                    let result = app.export_calendar();
+                    app.report(result);
+                }
+                (Some(Action::Sync), _) => {
+                    let result = app.sync();
+                    app.report(result);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::tests::scratch_db;
+    use crate::db::{Priority, Status, Task, TaskList};
+    use crate::recurrence::Recurrence;
+    use crate::state::State;
+    use crate::theme::Theme;
+    use chrono::{Duration, Local, NaiveDate, Utc};
+    use crossterm::event::KeyModifiers;
+    use tui::style::Color;
+
+    pub(crate) fn list(id: usize, name: &str, tasks: &[&str]) -> TaskList {
+        TaskList {
+            tasks: tasks
+                .iter()
+                .enumerate()
+                .map(|(i, task)| Task::new(i, task.to_string(), Utc::now() + Duration::days(30)))
+                .collect(),
+            ..TaskList::new(id, name.to_string())
+        }
+    }
+
+    pub(crate) fn app(lists: Vec<TaskList>) -> App {
+        App::with_tasks(Config::default(), State::default(), scratch_db(), lists)
+    }
+
+    pub(crate) fn press_code(app: &mut App, code: KeyCode) {
+        handle_key(app, KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    pub(crate) fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            press_code(app, KeyCode::Char(c));
+        }
+    }
+
+    pub(crate) fn names(app: &App, list: usize) -> Vec<&str> {
+        app.tasklists[list]
+            .tasks
+            .iter()
+            .map(|task| task.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn list_selection_stops_at_the_ends() {
+        let mut app = app(vec![list(0, "One", &[]), list(1, "Two", &[])]);
+        press(&mut app, "jjjjjjjjjj");
+        assert_eq!(app.selected_entry(), app.sidebar_len() - 1);
+        press(&mut app, "kkkkkkkkkk");
+        assert_eq!(app.selected_entry(), 0);
+    }
+
+    #[test]
+    fn list_selection_wraps_when_configured() {
+        let mut app = app(vec![list(0, "One", &[]), list(1, "Two", &[])]);
+        app.config.wrap_around = true;
+        press(&mut app, "k");
+        assert_eq!(app.selected_entry(), app.sidebar_len() - 1);
+        press(&mut app, "j");
+        assert_eq!(app.selected_entry(), 0);
+    }
+
+    #[test]
+    fn empty_list_keeps_focus_on_the_lists() {
+        let mut app = app(vec![list(0, "Empty", &[])]);
+        press(&mut app, "ljkxv ");
+        assert_eq!(app.task_state.selected(), None);
+        assert!(app.marked.is_none());
+    }
+
+    #[test]
+    fn task_selection_stops_at_the_last_task() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "l");
+        assert_eq!(app.task_state.selected(), Some(0));
+        press(&mut app, "jjjjj");
+        assert_eq!(app.task_state.selected(), Some(2));
+        press(&mut app, "h");
+        assert_eq!(app.task_state.selected(), None);
+    }
+
+    #[test]
+    fn deleting_the_last_rows_clamps_the_selection() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "ljjv dy");
+        assert_eq!(names(&app, 0), ["a", "b"]);
+        assert_eq!(app.task_state.selected(), Some(1));
+        assert!(app.marked.is_none());
+        // Saved right away
+        assert!(!app.dirty);
+    }
+
+    #[test]
+    fn deleting_every_task_drops_the_selection() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        press(&mut app, "lv j dy");
+        assert!(names(&app, 0).is_empty());
+        assert_eq!(app.task_state.selected(), None);
+    }
+
+    #[test]
+    fn esc_leaves_visual_mode() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        press(&mut app, "lv ");
+        assert_eq!(app.marked.as_ref().map(|marked| marked.len()), Some(1));
+        press_code(&mut app, KeyCode::Esc);
+        assert!(app.marked.is_none());
+        assert_eq!(names(&app, 0), ["a", "b"]);
+    }
+
+    #[test]
+    fn command_prompt_creates_and_selects_a_list() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, ":new list Work");
+        assert_eq!(
+            app.prompt.as_ref().map(|p| p.input.as_str()),
+            Some("new list Work")
+        );
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.prompt.is_none());
+        assert_eq!(app.tasklists.len(), 2);
+        assert_eq!(app.selected_list(), Some(1));
+        assert_eq!(app.view_name(), "Work");
+    }
+
+    #[test]
+    fn duplicate_list_name_asks_first() {
+        let mut app = app(vec![list(0, "Work", &["a"])]);
+        press(&mut app, ":new list work");
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.confirm.is_some());
+        press(&mut app, "n");
+        assert!(app.confirm.is_none());
+        assert_eq!(app.tasklists.len(), 1);
+        press(&mut app, ":new list work");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "y");
+        assert_eq!(app.tasklists.len(), 2);
+    }
+
+    #[test]
+    fn moving_a_task_to_the_top_keeps_it_selected() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "ljjT");
+        assert_eq!(names(&app, 0), ["c", "a", "b"]);
+        assert_eq!(app.task_state.selected(), Some(0));
+        press(&mut app, "B");
+        assert_eq!(names(&app, 0), ["a", "b", "c"]);
+        assert_eq!(app.task_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn search_selects_the_best_match() {
+        let mut app = app(vec![list(0, "One", &["groceries", "laundry", "taxes"])]);
+        press(&mut app, "l/lau");
+        assert_eq!(app.task_state.selected(), Some(1));
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.search.is_none());
+        assert_eq!(app.task_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn blocked_tasks_cannot_be_completed() {
+        let mut lists = vec![list(0, "One", &["a", "b"])];
+        lists[0].tasks[1].depends_on.push(0);
+        let mut app = app(lists);
+        press(&mut app, "ljx");
+        assert!(!app.tasklists[0].tasks[1].is_completed());
+        press(&mut app, "kx");
+        assert!(app.tasklists[0].tasks[0].is_completed());
+        press(&mut app, "jx");
+        assert!(app.tasklists[0].tasks[1].is_completed());
+    }
+
+    #[test]
+    fn focus_mode_completes_the_soonest_task() {
+        let mut lists = vec![list(0, "One", &["later", "sooner"])];
+        lists[0].tasks[1].due_date = Utc::now() + Duration::days(1);
+        let mut app = app(lists);
+        press(&mut app, "f");
+        assert_eq!(app.next_action(), Some((0, 1)));
+        press(&mut app, "x");
+        assert!(app.tasklists[0].tasks[1].is_completed());
+        assert_eq!(app.next_action(), Some((0, 0)));
+        press_code(&mut app, KeyCode::Esc);
+        assert!(!app.focus);
+    }
+
+    #[test]
+    fn status_cycles_through_done_and_back() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, "l");
+        let status = |app: &App| app.tasklists[0].tasks[0].status;
+        press(&mut app, "S");
+        assert_eq!(status(&app), Status::InProgress);
+        press(&mut app, "S");
+        assert_eq!(status(&app), Status::Waiting);
+        press(&mut app, "S");
+        assert_eq!(status(&app), Status::Done);
+        assert!(app.tasklists[0].tasks[0].is_completed());
+        press(&mut app, "S");
+        assert_eq!(status(&app), Status::Todo);
+        assert!(!app.tasklists[0].tasks[0].is_completed());
+    }
+
+    #[test]
+    fn priority_sort_puts_urgent_tasks_first() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "ljP");
+        assert_eq!(app.tasklists[0].tasks[1].priority, Priority::High);
+        press(&mut app, "jPPP");
+        assert_eq!(app.tasklists[0].tasks[2].priority, Priority::Low);
+        press(&mut app, "kP");
+        press(&mut app, ":sort priority");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(names(&app, 0), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn subtasks_are_added_checked_off_and_deleted() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, "l");
+        press_code(&mut app, KeyCode::Tab);
+        for name in ["one", "two", "three"] {
+            press(&mut app, "a");
+            press(&mut app, name);
+            press_code(&mut app, KeyCode::Enter);
+        }
+        assert_eq!(app.mode(), Mode::Checklist);
+        press(&mut app, "kxkx");
+        let task = &app.tasklists[0].tasks[0];
+        assert_eq!(task.subtask_progress(), Some((2, 3)));
+        press(&mut app, "jd");
+        let task = &app.tasklists[0].tasks[0];
+        let names: Vec<&str> = task.subtasks.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["one", "three"]);
+        assert_eq!(task.subtask_progress(), Some((1, 2)));
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode(), Mode::Normal);
+    }
+
+    #[test]
+    fn completing_a_recurring_task_adds_the_next_one() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, "lF");
+        press(&mut app, "weekly");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "x");
+        let tasks = &app.tasklists[0].tasks;
+        assert_eq!(names(&app, 0), ["a", "a"]);
+        assert!(tasks[0].is_completed() && tasks[0].recurrence.is_none());
+        assert!(!tasks[1].is_completed());
+        assert_eq!(tasks[1].recurrence, Some(Recurrence::Weekly));
+        let local = |task: &Task| task.due_date.with_timezone(&Local).naive_local();
+        assert_eq!(local(&tasks[1]) - local(&tasks[0]), Duration::weeks(1));
+    }
+
+    #[test]
+    fn undo_and_redo_step_through_changes() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        press(&mut app, "ljdy");
+        press(&mut app, "x");
+        assert_eq!(names(&app, 0), ["a"]);
+        assert!(app.tasklists[0].tasks[0].is_completed());
+        press(&mut app, "u");
+        assert!(!app.tasklists[0].tasks[0].is_completed());
+        press(&mut app, "u");
+        assert_eq!(names(&app, 0), ["a", "b"]);
+        assert!(app.trash.is_empty());
+        press(&mut app, "u");
+        assert_eq!(names(&app, 0), ["a", "b"]);
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        );
+        assert_eq!(names(&app, 0), ["a"]);
+        assert!(!app.tasklists[0].tasks[0].is_completed());
+    }
+
+    #[test]
+    fn undo_stops_at_moving_a_list_to_another_db() {
+        let mut app = app(vec![list(0, "One", &["a", "b"]), list(1, "Two", &["c"])]);
+        press(&mut app, "ljdy");
+        press(&mut app, "hj");
+        let other = scratch_db();
+        press(&mut app, &format!(":move list {}", other.display()));
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.tasklists.len(), 1);
+        press(&mut app, "u");
+        assert_eq!(app.tasklists.len(), 1);
+        assert_eq!(names(&app, 0), ["a"]);
+        let moved = crate::db::read_db(&other).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].name, "Two");
+    }
+
+    #[test]
+    fn shift_d_copies_a_task_without_its_progress() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        let a = &mut app.tasklists[0].tasks[0];
+        a.tags = vec!["home".to_string()];
+        a.status = Status::Done;
+        a.completed_at = Some(Utc::now());
+        a.time_spent = 600;
+        a.depends_on = vec![1];
+        press(&mut app, "lD");
+        assert_eq!(names(&app, 0), ["a", "a (copy)", "b"]);
+        assert_eq!(app.task_state.selected(), Some(1));
+        let (a, copy) = (&app.tasklists[0].tasks[0], &app.tasklists[0].tasks[1]);
+        assert_eq!(copy.id, 2);
+        assert_eq!(copy.tags, ["home"]);
+        assert_eq!((copy.start_date, copy.due_date), (a.start_date, a.due_date));
+        assert_eq!(copy.status, Status::Todo);
+        assert!(!copy.is_completed());
+        assert_eq!(copy.time_spent, 0);
+        assert!(copy.depends_on.is_empty());
+    }
+
+    #[test]
+    fn m_moves_the_selected_task_to_another_list() {
+        let mut app = app(vec![list(0, "One", &["a", "b"]), list(1, "Two", &["c"])]);
+        press(&mut app, "ljmtwo");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(names(&app, 0), ["a"]);
+        assert_eq!(names(&app, 1), ["c", "b"]);
+        assert_eq!(app.tasklists[1].tasks[1].id, 1);
+        assert!(!app.dirty);
+    }
+
+    #[test]
+    fn shift_j_and_k_reorder_tasks() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "lJJ");
+        assert_eq!(names(&app, 0), ["b", "c", "a"]);
+        assert_eq!(app.task_state.selected(), Some(2));
+        press(&mut app, "JK");
+        assert_eq!(names(&app, 0), ["b", "a", "c"]);
+        assert_eq!(app.task_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn slash_on_the_lists_searches_every_list() {
+        let mut app = app(vec![
+            list(0, "One", &["apple", "banana"]),
+            list(1, "Two", &["cherry", "bandana"]),
+        ]);
+        app.tasklists[0].tasks[0].tags.push("errand".to_string());
+        press(&mut app, "/band");
+        assert_eq!(app.mode(), Mode::Find);
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.selected_task(), Some((1, 1)));
+        // Tags match too
+        press(&mut app, "h/errand");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.selected_task(), Some((0, 0)));
+    }
+
+    #[test]
+    fn tag_picker_filters_by_the_picked_tags() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        app.tasklists[0].tasks[0].tags.push("home".to_string());
+        app.tasklists[0].tasks[1].tags.push("work".to_string());
+        press(&mut app, "t");
+        assert_eq!(app.mode(), Mode::TagPicker);
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.visible_tasks(), [(0, 0)]);
+        press(&mut app, "tj ");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.visible_tasks(), [(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn keys_can_be_rebound_with_underscored_names() {
+        let mut config = Config::default();
+        config.keys.insert("down".to_string(), "n".to_string());
+        config
+            .keys
+            .insert("move_down".to_string(), "ctrl-j".to_string());
+        let lists = vec![list(0, "One", &["a", "b", "c"])];
+        let mut app = App::with_tasks(config, State::default(), scratch_db(), lists);
+        assert!(app.flash.is_none());
+        press(&mut app, "ln");
+        assert_eq!(app.task_state.selected(), Some(1));
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL),
+        );
+        assert_eq!(names(&app, 0), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn tag_list_command_tags_and_stamps_untagged_tasks() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        app.tasklists[0].tasks[0].tags = vec!["home".to_string()];
+        press(&mut app, ":tag list home");
+        press_code(&mut app, KeyCode::Enter);
+        let tasks = &app.tasklists[0].tasks;
+        assert_eq!(tasks[1].tags, ["home"]);
+        assert!(tasks[0].updated_at.is_none());
+        assert!(tasks[1].updated_at.is_some());
+    }
+
+    #[test]
+    fn theme_colors_can_be_overridden() {
+        let mut config = Config {
+            theme: "light".to_string(),
+            ..Config::default()
+        };
+        config
+            .colors
+            .insert("selected_bg".to_string(), "#102030".to_string());
+        config
+            .colors
+            .insert("priority-medium".to_string(), "gray".to_string());
+        config
+            .colors
+            .insert("borders".to_string(), "red".to_string());
+        let app = App::with_tasks(config, State::default(), scratch_db(), Vec::new());
+        assert_eq!(app.theme.text, Theme::LIGHT.text);
+        assert_eq!(app.theme.selected_bg, Color::Rgb(16, 32, 48));
+        assert_eq!(app.theme.priority_medium, Color::Gray);
+        assert!(app.flash.as_ref().is_some_and(|flash| flash.error));
+    }
+
+    #[test]
+    fn deleted_task_is_restored_in_place() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "ljdn");
+        assert_eq!(names(&app, 0), ["a", "b", "c"]);
+        press(&mut app, "dy");
+        assert_eq!(names(&app, 0), ["a", "c"]);
+        press(&mut app, "U");
+        assert_eq!(names(&app, 0), ["a", "b", "c"]);
+        assert_eq!(app.task_state.selected(), Some(1));
+        assert!(app.trash.is_empty());
+    }
+
+    #[test]
+    fn n_and_shift_n_step_through_search_matches() {
+        let mut app = app(vec![list(
+            0,
+            "One",
+            &["milk", "bread", "more milk", "eggs"],
+        )]);
+        press(&mut app, "l/milk");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.task_state.selected(), Some(0));
+        press(&mut app, "n");
+        assert_eq!(app.task_state.selected(), Some(2));
+        press(&mut app, "n");
+        assert_eq!(app.task_state.selected(), Some(0));
+        press(&mut app, "N");
+        assert_eq!(app.task_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn auto_sorted_list_stays_sorted_after_a_snooze() {
+        let mut lists = vec![list(0, "One", &["a", "b"])];
+        lists[0].tasks[1].due_date += Duration::hours(1);
+        let mut app = app(lists);
+        press(&mut app, ":sort due auto");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "l+");
+        assert_eq!(names(&app, 0), ["b", "a"]);
+        assert_eq!(app.task_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn snooze_cannot_move_due_before_start() {
+        let mut app = App::with_tasks(
+            Config {
+                snooze: "-100000".to_string(),
+                ..Config::default()
+            },
+            State::default(),
+            scratch_db(),
+            vec![list(0, "One", &["a"])],
+        );
+        let due = app.tasklists[0].tasks[0].due_date;
+        press(&mut app, "l+");
+        assert_eq!(app.tasklists[0].tasks[0].due_date, due);
+        assert!(app.flash.as_ref().is_some_and(|flash| flash.error));
+    }
+
+    #[test]
+    fn clear_moves_completed_tasks_to_the_trash() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "lxjjx");
+        press(&mut app, ":clear");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(names(&app, 0), ["b"]);
+        assert_eq!(app.trash.len(), 2);
+    }
+
+    #[test]
+    fn tab_completes_the_tag_being_typed() {
+        let mut lists = vec![list(0, "One", &["a", "b"])];
+        lists[0].tasks[0].tags = vec!["work".to_string()];
+        lists[0].tasks[1].tags = vec!["home".to_string(), "Work".to_string()];
+        let mut app = app(lists);
+        assert_eq!(app.all_tags(), ["home", "work"]);
+        press(&mut app, "#home wk");
+        press_code(&mut app, KeyCode::Tab);
+        assert_eq!(
+            app.prompt.as_ref().map(|p| p.input.as_str()),
+            Some("home work")
+        );
+    }
+
+    #[test]
+    fn each_list_remembers_its_selected_task() {
+        let mut app = app(vec![
+            list(0, "One", &["a", "b", "c"]),
+            list(1, "Two", &["d", "e"]),
+        ]);
+        press(&mut app, "ljjh");
+        press(&mut app, "jljh");
+        press(&mut app, "kl");
+        assert_eq!(app.task_state.selected(), Some(2));
+        press(&mut app, "hjl");
+        assert_eq!(app.task_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn g_jumps_to_a_list_by_name() {
+        let mut app = app(vec![
+            list(0, "Work", &["a"]),
+            list(1, "Home", &["b"]),
+            list(2, "Errands", &["c"]),
+        ]);
+        press(&mut app, "lgerr");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.selected_list(), Some(2));
+        assert_eq!(app.task_state.selected(), None);
+    }
+
+    #[test]
+    fn pinned_lists_come_first_in_the_sidebar() {
+        let mut app = app(vec![
+            list(0, "One", &["a"]),
+            list(1, "Two", &["b"]),
+            list(2, "Three", &["c"]),
+        ]);
+        press(&mut app, "jjp");
+        assert_eq!(app.sidebar_lists(), [2, 0, 1]);
+        assert_eq!(app.selected_entry(), 0);
+        press(&mut app, "jK");
+        assert_eq!(names(&app, 0), ["a"]);
+        assert_eq!(app.selected_list(), Some(0));
+    }
+
+    #[test]
+    fn editing_a_task_records_when() {
+        let mut lists = vec![list(0, "One", &["a", "b"])];
+        for task in &mut lists[0].tasks {
+            task.start_date -= Duration::days(30);
+        }
+        let mut app = app(lists);
+        press(&mut app, "lS");
+        let tasks = &app.tasklists[0].tasks;
+        assert!(Utc::now() - tasks[0].last_edited() < Duration::minutes(1));
+        assert_eq!(tasks[1].last_edited(), tasks[1].start_date);
+    }
+
+    #[test]
+    fn shift_moves_every_open_due_date() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        let due: Vec<_> = app.tasklists[0].tasks.iter().map(|t| t.due_date).collect();
+        press(&mut app, "lx:shift +1w");
+        press_code(&mut app, KeyCode::Enter);
+        let tasks = &app.tasklists[0].tasks;
+        assert_eq!(tasks[0].due_date, due[0]);
+        assert_eq!(tasks[1].due_date, due[1] + Duration::weeks(1));
+        press(&mut app, ":shift -100d");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.tasklists[0].tasks[1].due_date,
+            due[1] + Duration::weeks(1)
+        );
+    }
+
+    #[test]
+    fn esc_leaves_every_mode() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        for keys in ["/a", ":sort", "c", "f", "lv "] {
+            press(&mut app, keys);
+            assert_ne!(app.mode(), Mode::Normal, "{}", keys);
+            press_code(&mut app, KeyCode::Esc);
+            assert_eq!(app.mode(), Mode::Normal, "{}", keys);
+        }
+        assert_eq!(app.tasklists[0].tasks.len(), 2);
+        press(&mut app, ":status done");
+        press_code(&mut app, KeyCode::Enter);
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(app.status_filter, None);
+    }
+
+    #[test]
+    fn urgency_sort_floats_overdue_lists_up() {
+        let mut lists = vec![list(0, "Calm", &["a"]), list(1, "Late", &["b", "c"])];
+        lists[1].tasks[0].due_date = Utc::now() - Duration::days(1);
+        let mut app = app(lists);
+        press(&mut app, "!");
+        assert_eq!(app.sidebar_lists(), [1, 0]);
+        assert_eq!(app.selected_list(), Some(0));
+        press(&mut app, "!");
+        assert_eq!(app.sidebar_lists(), [0, 1]);
+    }
+
+    #[test]
+    fn comments_are_appended_with_a_time() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, "lAstarted");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "Ahalfway");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "A");
+        press_code(&mut app, KeyCode::Enter);
+        let comments = &app.tasklists[0].tasks[0].comments;
+        let texts: Vec<&str> = comments.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["started", "halfway"]);
+        assert!(comments[0].at <= comments[1].at);
+    }
+
+    #[test]
+    fn form_adds_a_task_to_the_selected_list() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        press(&mut app, "aBuy milk");
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "home, #errand");
+        press_code(&mut app, KeyCode::Tab);
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "soon");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode(), Mode::Form);
+        for _ in 0..4 {
+            press_code(&mut app, KeyCode::Backspace);
+        }
+        press(&mut app, "3d");
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode(), Mode::Normal);
+        let task = &app.tasklists[0].tasks[1];
+        assert_eq!(task.name, "Buy milk");
+        assert_eq!(task.tags, ["home", "errand"]);
+        assert!(task.due_date - task.start_date > Duration::days(2));
+        assert_eq!(app.task_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn d_on_the_list_panel_deletes_the_list_once_confirmed() {
+        let mut app = app(vec![list(0, "One", &["a"]), list(1, "Two", &["b"])]);
+        press(&mut app, "dn");
+        assert_eq!(app.tasklists.len(), 2);
+        press(&mut app, "dy");
+        assert_eq!(app.tasklists.len(), 1);
+        assert_eq!(app.tasklists[0].name, "Two");
+        assert!(app.db_path.exists());
+        press(&mut app, "dy");
+        assert_eq!(app.tasklists.len(), 1);
+    }
+
+    #[test]
+    fn e_edits_the_task_and_keeps_untouched_dates() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        let before = app.tasklists[0].tasks[1].clone();
+        press(&mut app, "lje");
+        assert_eq!(app.form.as_ref().map(|f| f.fields[0].as_str()), Some("b"));
+        press_code(&mut app, KeyCode::Backspace);
+        press(&mut app, "renamed");
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "new");
+        press_code(&mut app, KeyCode::Enter);
+        let task = &app.tasklists[0].tasks[1];
+        assert_eq!(task.name, "renamed");
+        assert_eq!(task.tags, ["new"]);
+        assert_eq!(task.start_date, before.start_date);
+        assert_eq!(task.due_date, before.due_date);
+        press(&mut app, "e");
+        press_code(&mut app, KeyCode::Backspace);
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(app.tasklists[0].tasks[1].name, "renamed");
+    }
+
+    #[test]
+    fn autosave_writes_changes_without_leaving_temp_files() {
+        let mut app = App::with_tasks(
+            Config {
+                autosave: true,
+                ..Config::default()
+            },
+            State::default(),
+            scratch_db(),
+            vec![list(0, "One", &["a"])],
+        );
+        press(&mut app, "lx");
+        app.autosave().unwrap();
+        assert!(!app.dirty);
+        let dir = app.db_path.parent().unwrap();
+        let mut files: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["db.json", "db.state.json", "db.trash.json"]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_saves_tags_and_single_lists() {
+        let path = scratch_db().with_file_name("db.sqlite");
+        let lists = vec![list(0, "One", &["a", "b"]), list(1, "Two", &["c"])];
+        crate::db::write_db(&path, &lists).unwrap();
+        let lists = crate::db::read_db(&path).unwrap();
+        let mut app = App::with_tasks(Config::default(), State::default(), path, lists);
+        press(&mut app, "lje");
+        press_code(&mut app, KeyCode::Tab);
+        press(&mut app, "home work");
+        press_code(&mut app, KeyCode::Enter);
+        app.save().unwrap();
+        let saved = crate::db::read_db(&app.db_path).unwrap();
+        assert_eq!(saved[0].tasks[1].tags, ["home", "work"]);
+        assert!(saved[0].tasks[0].tags.is_empty());
+        assert_eq!(saved[1].tasks[0].name, "c");
+    }
+
+    #[test]
+    fn todotxt_import_sorts_tasks_into_project_lists() {
+        let mut app = app(vec![list(0, "Work", &["a"])]);
+        let path = app.db_path.with_file_name("todo.txt");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "(A) 2024-03-01 Call Mom +Family @phone due:2024-03-05\n\
+             x 2024-03-02 2024-03-01 Send report +work pri:B\n\
+             \n\
+             Water plants\n",
+        )
+        .unwrap();
+        press(&mut app, &format!(":import todotxt {}", path.display()));
+        press_code(&mut app, KeyCode::Enter);
+        let names: Vec<&str> = app.tasklists.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["Work", "Family", "Inbox"]);
+        let call = &app.tasklists[1].tasks[0];
+        assert_eq!(call.name, "Call Mom");
+        assert_eq!(call.priority, Priority::Urgent);
+        assert_eq!(call.tags, ["phone"]);
+        let due = call.due_date.with_timezone(&Local).date_naive();
+        assert_eq!(due.to_string(), "2024-03-05");
+        let report = &app.tasklists[0].tasks[1];
+        assert_eq!((report.id, report.name.as_str()), (1, "Send report"));
+        assert!(report.is_completed());
+        assert_eq!(report.priority, Priority::High);
+        assert_eq!(app.tasklists[2].tasks[0].name, "Water plants");
+        assert!(app.dirty);
+    }
+
+    #[test]
+    fn space_checks_off_and_shift_x_hides_completed() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        press(&mut app, "l ");
+        assert!(app.tasklists[0].tasks[0].is_completed());
+        press(&mut app, "jX");
+        assert_eq!(app.visible_tasks(), [(0, 1), (0, 2)]);
+        assert_eq!(app.task_state.selected(), Some(0));
+        press(&mut app, " ");
+        assert_eq!(app.visible_tasks(), [(0, 2)]);
+        press(&mut app, "X");
+        assert_eq!(app.visible_tasks().len(), 3);
+        assert_eq!(app.task_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn countdown_command_switches_due_dates_to_time_left() {
+        let mut app = app(vec![list(0, "One", &["a"])]);
+        assert!(!app.countdown);
+        press(&mut app, ":countdown");
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.countdown);
+    }
+
+    #[test]
+    fn shift_g_opens_the_agenda_grouped_by_when_tasks_are_due() {
+        use crate::agenda::{agenda_lines, AgendaLine};
+        let mut app = app(vec![
+            list(0, "One", &["later", "late", "soon"]),
+            list(1, "Two", &["x"]),
+        ]);
+        app.tasklists[0].tasks[1].due_date = Utc::now() - Duration::days(2);
+        app.tasklists[0].tasks[2].due_date = Utc::now() + Duration::days(1);
+        app.tasklists[1].tasks[0].completed_at = Some(Utc::now());
+        press(&mut app, "jG");
+        assert!(app.view() == crate::app::View::Agenda);
+        let visible = app.visible_tasks();
+        assert_eq!(visible, [(0, 1), (0, 2), (0, 0)]);
+        let lines = agenda_lines(&app, &visible);
+        assert_eq!(lines[..2], [AgendaLine::Overdue, AgendaLine::Task(0)]);
+        let soon = app.task(visible[1]).due_date.with_timezone(&Local);
+        let day = lines
+            .iter()
+            .position(|&l| l == AgendaLine::Day(soon.date_naive()));
+        assert_eq!(lines[day.unwrap() + 1], AgendaLine::Task(1));
+        assert_eq!(
+            lines[lines.len() - 2..],
+            [AgendaLine::Later, AgendaLine::Task(2)]
+        );
+        press(&mut app, "G");
+        assert!(app.view() == crate::app::View::List(1));
+    }
+
+    #[test]
+    fn calendar_moves_by_day_week_and_month_then_filters_to_a_day() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        press(&mut app, "c");
+        app.calendar = date(2026, 1, 31);
+        press(&mut app, "L");
+        assert_eq!(app.calendar, date(2026, 2, 28));
+        press(&mut app, "hkH");
+        assert_eq!(app.calendar, date(2026, 1, 20));
+        let due = app.tasklists[0].tasks[1].due_date.with_timezone(&Local);
+        app.calendar = Some(due.date_naive());
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.calendar, None);
+        assert_eq!(app.visible_tasks(), [(0, 0), (0, 1)]);
+        app.tasklists[0].tasks[0].due_date += Duration::days(1);
+        assert_eq!(app.visible_tasks(), [(0, 1)]);
+        press_code(&mut app, KeyCode::Esc);
+        assert_eq!(app.visible_tasks().len(), 2);
+    }
+
+    #[test]
+    fn board_moves_tasks_between_status_columns() {
+        let mut app = app(vec![list(0, "One", &["a", "b", "c"])]);
+        app.tasklists[0].tasks[1].status = Status::InProgress;
+        press(&mut app, "lj|");
+        assert_eq!(app.board_task(), Some((0, 1)));
+        press(&mut app, "hj");
+        assert_eq!(app.board_task(), Some((0, 2)));
+        press(&mut app, "LL");
+        assert_eq!(app.tasklists[0].tasks[2].status, Status::Waiting);
+        assert_eq!(app.board_task(), Some((0, 2)));
+        press(&mut app, "L");
+        assert!(app.tasklists[0].tasks[2].is_completed());
+        press(&mut app, "H");
+        assert!(!app.tasklists[0].tasks[2].is_completed());
+        assert_eq!(app.tasklists[0].tasks[2].status, Status::Waiting);
+        assert!(app.dirty);
+        press(&mut app, "hh");
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.board.is_none());
+        assert_eq!(app.selected_task(), Some((0, 0)));
+    }
+
+    #[test]
+    fn enter_opens_the_list_then_toggles_the_details_pane() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        press_code(&mut app, KeyCode::Enter);
+        assert_eq!(app.selected_task(), Some((0, 0)));
+        assert!(!app.show_details);
+        press_code(&mut app, KeyCode::Enter);
+        assert!(app.show_details);
+        // The pane follows the selection
+        press(&mut app, "j");
+        assert!(app.show_details);
+        assert_eq!(app.selected_task(), Some((0, 1)));
+        press_code(&mut app, KeyCode::Enter);
+        assert!(!app.show_details);
+    }
+}
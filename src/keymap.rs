@@ -0,0 +1,245 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Quit,
+    ForceQuit,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Command,
+    GoToList,
+    ShrinkLists,
+    GrowLists,
+    Collapse,
+    Dense,
+    Calendar,
+    Agenda,
+    Board,
+    Details,
+    Capture,
+    Add,
+    Edit,
+    ReverseSort,
+    ScrollLeft,
+    ScrollRight,
+    TagFilter,
+    TagMode,
+    Left,
+    Down,
+    Up,
+    Right,
+    Visual,
+    Mark,
+    Delete,
+    Restore,
+    Move,
+    Tag,
+    Estimate,
+    Comment,
+    Subtasks,
+    Repeat,
+    Reminder,
+    Snooze,
+    SnoozeWeek,
+    Timer,
+    Complete,
+    HideCompleted,
+    Status,
+    Priority,
+    BlockedBy,
+    Yank,
+    Duplicate,
+    MoveToTop,
+    MoveToBottom,
+    Focus,
+    NewListBelow,
+    NewListAbove,
+    MoveDown,
+    MoveUp,
+    ListColor,
+    Pin,
+    UrgencySort,
+    Hints,
+    Undo,
+    Redo,
+    ExportIcs,
+    Sync,
+}
+
+/// Every action with its name in the config and its default key.
+const ACTIONS: [(Action, &str, &str); 63] = [
+    (Action::Quit, "quit", "q"),
+    (Action::ForceQuit, "force-quit", "ctrl-q"),
+    (Action::Search, "search", "/"),
+    (Action::SearchNext, "search-next", "n"),
+    (Action::SearchPrevious, "search-previous", "N"),
+    (Action::Command, "command", ":"),
+    (Action::GoToList, "go-to-list", "g"),
+    (Action::ShrinkLists, "shrink-lists", "<"),
+    (Action::GrowLists, "grow-lists", ">"),
+    (Action::Collapse, "collapse", "z"),
+    (Action::Dense, "dense", "Z"),
+    (Action::Calendar, "calendar", "c"),
+    (Action::Agenda, "agenda", "G"),
+    (Action::Board, "board", "|"),
+    (Action::Details, "details", "enter"),
+    (Action::Capture, "capture", "i"),
+    (Action::Add, "add", "a"),
+    (Action::Edit, "edit", "e"),
+    (Action::ReverseSort, "reverse-sort", "R"),
+    (Action::ScrollLeft, "scroll-left", "H"),
+    (Action::ScrollRight, "scroll-right", "L"),
+    (Action::TagFilter, "tag-filter", "#"),
+    (Action::TagMode, "tag-mode", "&"),
+    (Action::Left, "left", "h"),
+    (Action::Down, "down", "j"),
+    (Action::Up, "up", "k"),
+    (Action::Right, "right", "l"),
+    (Action::Visual, "visual", "v"),
+    (Action::Mark, "mark", "space"),
+    (Action::Delete, "delete", "d"),
+    (Action::Restore, "restore", "U"),
+    (Action::Move, "move", "m"),
+    (Action::Tag, "tag", "t"),
+    (Action::Estimate, "estimate", "E"),
+    (Action::Comment, "comment", "A"),
+    (Action::Subtasks, "subtasks", "tab"),
+    (Action::Repeat, "repeat", "F"),
+    (Action::Reminder, "reminder", "r"),
+    (Action::Snooze, "snooze", "+"),
+    (Action::SnoozeWeek, "snooze-week", "W"),
+    (Action::Timer, "timer", "s"),
+    (Action::Complete, "complete", "x"),
+    (Action::HideCompleted, "hide-completed", "X"),
+    (Action::Status, "status", "S"),
+    (Action::Priority, "priority", "P"),
+    (Action::BlockedBy, "blocked-by", "b"),
+    (Action::Yank, "yank", "y"),
+    (Action::Duplicate, "duplicate", "D"),
+    (Action::MoveToTop, "move-to-top", "T"),
+    (Action::MoveToBottom, "move-to-bottom", "B"),
+    (Action::Focus, "focus", "f"),
+    (Action::NewListBelow, "new-list-below", "o"),
+    (Action::NewListAbove, "new-list-above", "O"),
+    (Action::MoveDown, "move-down", "J"),
+    (Action::MoveUp, "move-up", "K"),
+    (Action::ListColor, "list-color", "C"),
+    (Action::Pin, "pin", "p"),
+    (Action::UrgencySort, "urgency-sort", "!"),
+    (Action::Hints, "hints", "?"),
+    (Action::Undo, "undo", "u"),
+    (Action::Redo, "redo", "ctrl-r"),
+    (Action::ExportIcs, "export-ics", "I"),
+    (Action::Sync, "sync", "ctrl-s"),
+];
+
+/// A key code and whether Ctrl is held. Shift is already in the character.
+type Binding = (KeyCode, bool);
+
+/// Parses keys like "j", "K", "space" or "ctrl-q".
+fn parse_key(key: &str) -> Option<Binding> {
+    let (name, ctrl) = match key.strip_prefix("ctrl-") {
+        Some(name) => (name, true),
+        None => (key, false),
+    };
+    let code = match name {
+        "space" => KeyCode::Char(' '),
+        "esc" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some((code, ctrl))
+}
+
+/// Names a binding the way it's written in the config.
+fn key_name((code, ctrl): Binding) -> String {
+    let name = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        code => format!("{:?}", code),
+    };
+    if ctrl {
+        format!("ctrl-{}", name)
+    } else {
+        name
+    }
+}
+
+pub struct Keymap {
+    bindings: HashMap<Binding, Action>,
+}
+
+impl Keymap {
+    /// Builds the default bindings with `overrides` (action name to key)
+    /// applied on top. Bad entries are skipped and described in the returned
+    /// errors.
+    pub fn new(overrides: &HashMap<String, String>) -> (Keymap, Vec<String>) {
+        let mut keys: HashMap<Action, Binding> = ACTIONS
+            .iter()
+            .map(|&(action, _, key)| (action, parse_key(key).expect("default keys parse")))
+            .collect();
+        let mut errors = Vec::new();
+        for (name, key) in overrides {
+            // move_down reads as well as move-down
+            let action = match ACTIONS
+                .iter()
+                .find(|(_, n, _)| *n == name.replace('_', "-"))
+            {
+                Some(&(action, _, _)) => action,
+                None => {
+                    errors.push(format!("Unknown action in keys: {}", name));
+                    continue;
+                }
+            };
+            match parse_key(key) {
+                Some(binding) => {
+                    // A key does one thing, so whatever had it loses it
+                    keys.retain(|_, bound| *bound != binding);
+                    keys.insert(action, binding);
+                }
+                None => errors.push(format!("Unknown key for {}: {}", name, key)),
+            }
+        }
+        let bindings = keys
+            .into_iter()
+            .map(|(action, key)| (key, action))
+            .collect();
+        (Keymap { bindings }, errors)
+    }
+
+    /// The key bound to `action`, if it still has one.
+    pub fn key(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, &bound)| bound == action)
+            .map(|(&binding, _)| key_name(binding))
+    }
+
+    pub fn action(&self, event: KeyEvent) -> Option<Action> {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        self.bindings.get(&(event.code, ctrl)).copied()
+    }
+}
@@ -1,30 +1,126 @@
+use chrono::prelude::Local;
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, MouseButton, MouseEventKind,
+    },
+    execute,
     terminal::{disable_raw_mode, enable_raw_mode},
 };
+use log::{debug, LevelFilter};
+use simplelog::WriteLogger;
+use std::fs::{self, OpenOptions};
 use std::time::{Duration, Instant};
 use std::{io, sync::mpsc, thread};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table,
-        TableState,
+        Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table,
+        TableState, Wrap,
     },
     Terminal,
 };
 
+mod agenda;
+mod app;
+mod board;
+mod bucket;
+#[cfg(feature = "caldav")]
+mod caldav;
+mod calendar;
+mod cli;
+mod command;
+mod config;
+mod core;
 mod db;
+mod duration;
+mod export;
+mod form;
+mod headless;
+mod history;
+mod import;
+mod input;
+mod keymap;
+mod monochrome;
+mod natural;
+mod notify;
+mod recurrence;
+mod search;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod state;
+mod store;
+mod sync;
+mod taskwarrior;
+mod theme;
+mod todoist;
+mod todotxt;
+mod trash;
+use crate::agenda::*;
+use crate::app::*;
+use crate::board::*;
+use crate::bucket::*;
+use crate::calendar::*;
+use crate::config::*;
 use crate::db::*;
+use crate::duration::*;
+use crate::form::*;
+use crate::input::*;
+use crate::keymap::Action;
+use crate::monochrome::*;
+use crate::search::*;
+use crate::theme::Theme;
+
+const LOG_FILE: &str = "tbg.log";
+/// Width of the new task form
+const FORM_WIDTH: u16 = 50;
+/// Width of the popup for searching every list
+const FINDER_WIDTH: u16 = 70;
+/// How many results the search across lists shows at once
+const FINDER_RESULTS: usize = 10;
+/// How many tags the tag picker shows at once
+const TAG_PICKER_ROWS: usize = 12;
+/// Most comments listed under the task in focus mode
+const FOCUS_COMMENTS: usize = 5;
+/// Percent of the task panel the details of the selected task take up
+const DETAILS_WIDTH: u16 = 40;
+/// Smallest terminal the full layout fits in
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 10;
 
-enum Event<I> {
+enum Event<I, M> {
     Input(I),
+    Mouse(M),
     Tick,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = cli::command().get_matches();
+    let level = if matches.get_flag("verbose") {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    };
+    let data = data_dir();
+    fs::create_dir_all(&data)?;
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(data.join(LOG_FILE))?;
+    WriteLogger::init(level, simplelog::Config::default(), log_file)?;
+
+    let config = read_config();
+    let db_path = cli::db_path(&matches, &config, &data)?;
+    check_backend(&db_path)?;
+    if cli::run(&matches, &db_path, &config)? {
+        return Ok(());
+    }
+
+    // Loaded before touching the terminal so errors print normally
+    let mut app = App::new(config, db_path)?;
+
     enable_raw_mode().expect("can run in raw mode");
 
     let (tx, rx) = mpsc::channel();
@@ -37,167 +133,710 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .unwrap_or_else(|| Duration::from_secs(0));
 
             if event::poll(timeout).expect("poll works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
+                match event::read().expect("can read events") {
+                    CEvent::Key(key) => tx.send(Event::Input(key)).expect("can send events"),
+                    CEvent::Mouse(mouse) => tx.send(Event::Mouse(mouse)).expect("can send events"),
+                    _ => {}
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
+            if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
             }
         }
     });
 
-    let stdout = io::stdout();
+    let mut stdout = io::stdout();
+    execute!(stdout, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let mut list_state = ListState::default();
-    list_state.select(Some(0));
-    let mut task_state = TableState::default();
-    task_state.select(None);
-
-    let tasklists = read_db();
-    let mut task_len = tasklists[0].tasks.len() - 1;
+    let mut agenda_state = TableState::default();
+    // Catch up on reminders that came due while tbg was closed
+    let result = app.remind();
+    app.report(result);
 
-    loop {
+    let monochrome = colors_disabled();
+    while !app.quit {
         terminal.draw(|rect| {
             let size = rect.size();
+            if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+                app.list_area = Rect::default();
+                app.task_area = Rect::default();
+                let message = Paragraph::new("Terminal too small")
+                    .style(Style::default().fg(app.theme.error))
+                    .alignment(Alignment::Center);
+                rect.render_widget(message, size);
+                return;
+            }
+            // Dense mode drops the margin and title to fit more tasks
+            let (margin, title_height) = if app.dense { (0, 0) } else { (2, 3) };
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .margin(2)
-                .constraints([Constraint::Length(3), Constraint::Min(2)].as_ref())
+                .margin(margin)
+                .constraints(
+                    [
+                        Constraint::Length(title_height),
+                        Constraint::Min(2),
+                        Constraint::Length(1),
+                        Constraint::Length(app.show_hints as u16),
+                    ]
+                    .as_ref(),
+                )
                 .split(size);
 
             let title = Paragraph::new("Tasks But Good")
-                .style(Style::default().fg(Color::Red))
+                .style(Style::default().fg(app.theme.title))
                 .alignment(Alignment::Center)
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::Red))
+                        .style(Style::default().fg(app.theme.title))
                         .border_type(BorderType::Double),
                 );
 
             rect.render_widget(title, chunks[0]);
+            let list_constraints = if app.collapsed {
+                vec![Constraint::Percentage(100)]
+            } else {
+                vec![
+                    Constraint::Percentage(app.list_width),
+                    Constraint::Percentage(100 - app.list_width),
+                ]
+            };
             let list_chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
+                .constraints(list_constraints)
                 .split(chunks[1]);
-            let lists = render_lists(&tasklists);
-            let selected_list = list_state
-                .selected()
-                .expect("There must be a selected list");
-            let tasks = render_tasks(&tasklists[selected_list]);
-            task_len = tasklists[selected_list].tasks.len() - 1;
-            rect.render_stateful_widget(lists, list_chunks[0], &mut list_state);
-            rect.render_stateful_widget(tasks, list_chunks[1], &mut task_state);
+            let order: Vec<_> = app
+                .sidebar_lists()
+                .into_iter()
+                .map(|l| &app.tasklists[l])
+                .collect();
+            let lists = render_lists(&order, app.dense, app.urgency_sort, &app.theme);
+            let visible = app.visible_tasks();
+            let widths = task_widths(&app);
+            let tasks = render_tasks(&app, &visible, &widths);
+            if app.collapsed {
+                app.list_area = Rect::default();
+                app.task_area = list_chunks[0];
+            } else {
+                app.list_area = list_chunks[0];
+                app.task_area = list_chunks[1];
+                rect.render_stateful_widget(lists, app.list_area, &mut app.list_state);
+            }
+            if let Some(task) = app.selected_task().filter(|_| app.show_details) {
+                let panels = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Percentage(100 - DETAILS_WIDTH),
+                        Constraint::Percentage(DETAILS_WIDTH),
+                    ])
+                    .split(app.task_area);
+                app.task_area = panels[0];
+                rect.render_widget(render_details(&app, task), panels[1]);
+            }
+            // The agenda's day headers push the tasks down, so it's drawn
+            // with its own selection pointing at the task's line
+            let agenda = (app.view() == View::Agenda).then(|| agenda_lines(&app, &visible));
+            let selected_line = match &agenda {
+                Some(lines) => app
+                    .task_state
+                    .selected()
+                    .and_then(|row| line_of(lines, row)),
+                None => app.task_state.selected(),
+            };
+            if agenda.is_some() {
+                agenda_state.select(selected_line);
+                rect.render_stateful_widget(tasks, app.task_area, &mut agenda_state);
+            } else {
+                rect.render_stateful_widget(tasks, app.task_area, &mut app.task_state);
+            }
+            if let Some(day) = app.calendar {
+                let calendar = render_calendar(day, &due_counts(&app.tasklists), &app.theme);
+                rect.render_widget(Clear, chunks[1]);
+                rect.render_widget(calendar, chunks[1]);
+            }
+            if let Some(form) = &app.form {
+                let width = FORM_WIDTH.min(chunks[1].width);
+                let height = (FORM_FIELDS.len() as u16 + 2).min(chunks[1].height);
+                let area = Rect {
+                    x: chunks[1].x + (chunks[1].width - width) / 2,
+                    y: chunks[1].y + (chunks[1].height - height) / 2,
+                    width,
+                    height,
+                };
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_form(form, app.view_name(), &app.theme), area);
+            }
+            if let Some(picker) = &app.tag_picker {
+                let width = FORM_WIDTH.min(chunks[1].width);
+                let rows = picker.tags.len().min(TAG_PICKER_ROWS) as u16;
+                let height = (rows + 2).min(chunks[1].height);
+                let area = Rect {
+                    x: chunks[1].x + (chunks[1].width - width) / 2,
+                    y: chunks[1].y + (chunks[1].height - height) / 2,
+                    width,
+                    height,
+                };
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_tag_picker(picker, &app.theme), area);
+            }
+            if let Some(finder) = &app.finder {
+                let width = FINDER_WIDTH.min(chunks[1].width);
+                let height = (FINDER_RESULTS as u16 + 3).min(chunks[1].height);
+                let area = Rect {
+                    x: chunks[1].x + (chunks[1].width - width) / 2,
+                    y: chunks[1].y + (chunks[1].height - height) / 2,
+                    width,
+                    height,
+                };
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_finder(&app, finder), area);
+            }
+            if let (Some(row), Some(task)) = (app.checklist, app.selected_task()) {
+                let task = app.task(task);
+                let width = FORM_WIDTH.min(chunks[1].width);
+                let height = (task.subtasks.len().max(1) as u16 + 2).min(chunks[1].height);
+                let area = Rect {
+                    x: chunks[1].x + (chunks[1].width - width) / 2,
+                    y: chunks[1].y + (chunks[1].height - height) / 2,
+                    width,
+                    height,
+                };
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_checklist(task, row, &app.theme), area);
+            }
+            if app.focus {
+                rect.render_widget(Clear, chunks[1]);
+                rect.render_widget(render_focus(&app, chunks[1].height), chunks[1]);
+            }
+            if let Some(board) = app.board {
+                rect.render_widget(Clear, chunks[1]);
+                let n = Status::ALL.len();
+                let areas = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![Constraint::Ratio(1, n as u32); n])
+                    .split(chunks[1]);
+                for (column, tasks) in board_columns(&app).iter().enumerate() {
+                    let highlighted = column == board.column;
+                    let mut state = ListState::default();
+                    state.select(Some(board.row).filter(|_| highlighted && !tasks.is_empty()));
+                    let list = render_board_column(&app, Status::ALL[column], tasks, highlighted);
+                    rect.render_stateful_widget(list, areas[column], &mut state);
+                }
+            }
+            let suggestions = app.tag_suggestions();
+            if !suggestions.is_empty() {
+                // Just above the prompt, in the bottom left corner
+                let shown = suggestions.len().min(TAG_SUGGESTIONS) as u16;
+                let height = (shown + 2).min(chunks[1].height);
+                let area = Rect {
+                    x: chunks[1].x,
+                    y: chunks[1].bottom() - height,
+                    width: 30.min(chunks[1].width),
+                    height,
+                };
+                rect.render_widget(Clear, area);
+                rect.render_widget(render_suggestions(&suggestions, &app.theme), area);
+            }
+            let border = border_width(app.dense);
+            app.list_offset = scroll_offset(
+                app.list_offset,
+                app.list_state.selected(),
+                app.list_area.height.saturating_sub(2 * border),
+            );
+            app.task_offset = scroll_offset(
+                app.task_offset,
+                selected_line,
+                app.task_area.height.saturating_sub(2 * border + 1),
+            );
+            let status = if let Some(query) = &app.search {
+                Paragraph::new(format!("/{}", query))
+            } else if let Some(prompt) = &app.prompt {
+                Paragraph::new(format!("{}{}", prompt.kind.label(), prompt.input))
+            } else if let Some(confirm) = &app.confirm {
+                Paragraph::new(confirm.question.clone())
+                    .style(Style::default().fg(app.theme.active))
+            } else if let Some(flash) = &app.flash {
+                let color = if flash.error {
+                    app.theme.error
+                } else {
+                    app.theme.text
+                };
+                Paragraph::new(flash.text.clone()).style(Style::default().fg(color))
+            } else {
+                render_status(&app)
+            };
+            rect.render_widget(status, chunks[2]);
+            if app.show_hints {
+                rect.render_widget(render_hints(&app), chunks[3]);
+            }
+            if monochrome {
+                rect.render_widget(Monochrome(&app.theme), size);
+            }
         })?;
 
-        let list_len = tasklists.len() - 1;
+        let list_len = app.sidebar_len() - 1;
+        let visible = app.visible_tasks();
 
-        match rx.recv()? {
-            Event::Input(event) => match task_state.selected() {
-                Some(task_selected) => match event.code {
-                    KeyCode::Char('q') => {
-                        disable_raw_mode()?;
-                        terminal.show_cursor()?;
-                        break;
-                    }
-                    KeyCode::Char('h') => {
-                        task_state.select(None);
-                    }
-                    KeyCode::Char('j') => {
-                        if task_selected != task_len {
-                            task_state.select(Some(task_selected + 1));
-                        }
-                    }
-                    KeyCode::Char('k') => {
-                        if task_selected != 0 {
-                            task_state.select(Some(task_selected as usize - 1));
-                        }
-                    }
-                    _ => {}
-                },
-                None => match event.code {
-                    KeyCode::Char('q') => {
-                        disable_raw_mode()?;
-                        terminal.show_cursor()?;
-                        break;
-                    }
-                    KeyCode::Char('j') => {
-                        if let Some(selected) = list_state.selected() {
-                            if selected != list_len {
-                                list_state.select(Some(selected + 1));
-                            }
+        let event = rx.recv()?;
+        if let Event::Input(key) = &event {
+            debug!("key {:?}", key);
+        }
+        match event {
+            Event::Input(event) => handle_key(&mut app, event),
+            Event::Mouse(event) => {
+                if let MouseEventKind::Down(MouseButton::Left) = event.kind {
+                    // Lists start below the top border, tasks below the border and header
+                    let border = border_width(app.dense);
+                    if let Some(clicked) = row_at(
+                        app.list_area,
+                        border,
+                        0,
+                        app.list_offset,
+                        event.column,
+                        event.row,
+                    ) {
+                        if clicked <= list_len {
+                            app.leave_tasks();
+                            app.list_state.select(Some(clicked));
                         }
-                    }
-                    KeyCode::Char('k') => {
-                        if let Some(selected) = list_state.selected() {
-                            if selected != 0 {
-                                list_state.select(Some(selected - 1));
+                    } else if let Some(clicked) = row_at(
+                        app.task_area,
+                        border,
+                        1,
+                        app.task_offset,
+                        event.column,
+                        event.row,
+                    ) {
+                        let clicked = if app.view() == View::Agenda {
+                            match agenda_lines(&app, &visible).get(clicked) {
+                                Some(&AgendaLine::Task(row)) => Some(row),
+                                _ => None,
                             }
+                        } else {
+                            Some(clicked)
+                        };
+                        if let Some(clicked) = clicked.filter(|&row| row < visible.len()) {
+                            app.task_state.select(Some(clicked));
                         }
                     }
-                    KeyCode::Char('l') => {
-                        task_state.select(Some(0));
-                    }
-                    _ => {}
-                },
-            },
-            Event::Tick => {}
+                }
+            }
+            Event::Tick => {
+                let result = app.remind();
+                app.report(result);
+                let result = app.finish_todoist_sync();
+                app.report(result);
+                app.expire_flash();
+            }
         }
+        let result = app.autosave();
+        app.report(result);
     }
 
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    terminal.show_cursor()?;
+
     Ok(())
 }
 
-fn render_lists<'a>(lists: &Vec<TaskList>) -> List<'a> {
-    let tasks = Block::default()
-        .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title("Lists")
-        .border_type(BorderType::Plain);
-    let lists: Vec<_> = lists
+/// Mirrors how tui scrolls a list of single-line rows to keep `selected` in view.
+fn scroll_offset(offset: usize, selected: Option<usize>, height: u16) -> usize {
+    let height = height.max(1) as usize;
+    match selected {
+        None => 0,
+        Some(selected) if selected < offset => selected,
+        Some(selected) if selected >= offset + height => selected + 1 - height,
+        Some(_) => offset,
+    }
+}
+
+/// Lines taken by a panel's border on each side.
+fn border_width(dense: bool) -> u16 {
+    if dense {
+        0
+    } else {
+        1
+    }
+}
+
+/// Maps a click to a row index in a panel with a `border` wide border whose
+/// rows start `header` lines below it.
+fn row_at(
+    area: Rect,
+    border: u16,
+    header: u16,
+    offset: usize,
+    column: u16,
+    row: u16,
+) -> Option<usize> {
+    let top = border + header;
+    let inside = column >= area.left() + border
+        && column + border < area.right()
+        && row >= area.top() + top
+        && row + border < area.bottom();
+    if inside {
+        Some((row - area.top() - top) as usize + offset)
+    } else {
+        None
+    }
+}
+
+fn list_color(color: ListColor) -> Color {
+    match color {
+        ListColor::Red => Color::Red,
+        ListColor::Green => Color::Green,
+        ListColor::Yellow => Color::Yellow,
+        ListColor::Blue => Color::Blue,
+        ListColor::Magenta => Color::Magenta,
+        ListColor::Cyan => Color::Cyan,
+    }
+}
+
+/// Draws the lists with their overdue counts, followed by the due-date
+/// buckets and the completed view with their task counts.
+fn render_lists<'a>(
+    lists: &[&TaskList],
+    dense: bool,
+    urgency_sort: bool,
+    theme: &Theme,
+) -> List<'a> {
+    let tasks = if dense {
+        Block::default()
+    } else {
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(if urgency_sort {
+                "Lists by urgency"
+            } else {
+                "Lists"
+            })
+            .border_type(BorderType::Plain)
+    };
+    let now = Local::now();
+    let mut counts = [0; Bucket::ALL.len()];
+    let mut completed = 0;
+    for task in lists.iter().flat_map(|list| &list.tasks) {
+        if task.is_completed() {
+            completed += 1;
+            continue;
+        }
+        let bucket = Bucket::of(task.due_date, now);
+        counts[Bucket::ALL.iter().position(|&b| b == bucket).unwrap()] += 1;
+    }
+    let mut items: Vec<_> = lists
         .iter()
         .map(|list| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                list.name.clone(),
-                Style::default(),
-            )]))
+            let mut label = vec![Span::styled(list.name.clone(), Style::default())];
+            if list.pinned {
+                label.insert(0, Span::raw("^ "));
+            }
+            if let Some(color) = list.color {
+                // A block rather than colored text so it shows on the highlight
+                label.insert(
+                    0,
+                    Span::styled("■ ", Style::default().fg(list_color(color))),
+                );
+            }
+            let overdue = list
+                .tasks
+                .iter()
+                .filter(|task| {
+                    !task.is_completed() && Bucket::of(task.due_date, now) == Bucket::Overdue
+                })
+                .count();
+            if overdue > 0 {
+                label.push(Span::styled(
+                    format!(" (!{})", overdue),
+                    // Light so it still shows on the red highlight
+                    Style::default()
+                        .fg(theme.badge)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            ListItem::new(Spans::from(label))
         })
         .collect();
+    items.extend(Bucket::ALL.iter().zip(counts).map(|(bucket, count)| {
+        ListItem::new(Spans::from(vec![Span::styled(
+            format!("{} ({})", bucket.name(), count),
+            Style::default().fg(theme.marker),
+        )]))
+    }));
+    items.push(ListItem::new(Spans::from(vec![Span::styled(
+        format!("{} ({})", COMPLETED, completed),
+        Style::default().fg(theme.done),
+    )])));
+    items.push(ListItem::new(Spans::from(vec![Span::styled(
+        AGENDA,
+        Style::default().fg(theme.agenda_title),
+    )])));
 
-    List::new(lists).block(tasks).highlight_style(
+    List::new(items).block(tasks).highlight_style(
         Style::default()
-            .bg(Color::Red)
-            .fg(Color::Black)
+            .bg(theme.selected_bg)
+            .fg(theme.selected_fg)
             .add_modifier(Modifier::BOLD),
     )
 }
 
-fn render_tasks<'a>(list: &TaskList) -> Table<'a> {
-    let tasks: Vec<Row> = (*list.tasks)
-        .to_owned()
+/// Task table headers and their relative widths. Name is always shown, the
+/// rest scroll horizontally.
+pub const TASK_COLUMNS: [(&str, u32); 8] = [
+    ("Name", 30),
+    ("Status", 12),
+    ("Priority", 10),
+    ("Tags", 22),
+    ("Start Date", 18),
+    ("Due Date", 18),
+    ("Span", 10),
+    ("Time", 12),
+];
+
+/// Columns kept in dense mode: the name and due date
+const DENSE_COLUMNS: [usize; 2] = [0, 5];
+
+/// The column for the time between a task's start and due dates, which is
+/// only shown when turned on with `:span`
+const SPAN_COLUMN: usize = 6;
+
+/// Indices of the task columns shown, after scrolling right by the column
+/// offset.
+fn shown_columns(app: &App) -> Vec<usize> {
+    if app.dense {
+        return DENSE_COLUMNS.to_vec();
+    }
+    let mut shown = vec![0];
+    shown.extend(1 + app.column_offset..TASK_COLUMNS.len());
+    shown.retain(|&c| c != SPAN_COLUMN || app.show_span);
+    shown
+}
+
+fn task_widths(app: &App) -> Vec<Constraint> {
+    let shown = shown_columns(app);
+    let total: u32 = shown.iter().map(|&c| TASK_COLUMNS[c].1).sum();
+    shown
         .iter()
-        .map(|task| {
-            Row::new(vec![
-                Cell::from(Span::raw(task.name.to_owned())),
+        .map(|&c| Constraint::Ratio(TASK_COLUMNS[c].1, total))
+        .collect()
+}
+
+fn status_color(status: Status, theme: &Theme) -> Color {
+    match status {
+        Status::Todo => theme.text,
+        Status::InProgress => theme.active,
+        Status::Waiting => theme.waiting,
+        Status::Done => theme.done,
+    }
+}
+
+fn priority_color(priority: Priority, theme: &Theme) -> Color {
+    match priority {
+        Priority::Low => theme.muted,
+        Priority::Medium => theme.priority_medium,
+        Priority::High => theme.active,
+        Priority::Urgent => theme.error,
+    }
+}
+
+/// Colors a due date by how much time is left: red once it's passed, then
+/// orange, yellow and green as the configured thresholds are crossed.
+fn due_color(
+    app: &App,
+    due: chrono::DateTime<chrono::Utc>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Color {
+    let left = due - now;
+    let soon = parse_duration(&app.config.due_soon).unwrap_or_else(|| chrono::Duration::hours(3));
+    let near = parse_duration(&app.config.due_near).unwrap_or_else(|| chrono::Duration::days(1));
+    if left < chrono::Duration::zero() {
+        app.theme.error
+    } else if left < soon {
+        app.theme.due_soon
+    } else if left < near {
+        app.theme.due_near
+    } else {
+        app.theme.due_later
+    }
+}
+
+fn render_tasks<'a>(app: &App, visible: &[TaskRef], widths: &'a [Constraint]) -> Table<'a> {
+    let lists = &app.tasklists;
+    let view = app.view();
+    let query = app.search.as_deref();
+    let marked = app.marked.as_ref();
+    let date_format = &app.config.date_format;
+    let theme = &app.theme;
+    let shown = shown_columns(app);
+    let now = chrono::Utc::now();
+    let stale_after =
+        parse_duration(&app.config.stale_after).filter(|after| *after > chrono::Duration::zero());
+    let near = parse_duration(&app.config.due_near).unwrap_or_else(|| chrono::Duration::days(1));
+    let mut tasks: Vec<Row> = visible
+        .iter()
+        .map(|&(l, t)| {
+            let task = &lists[l].tasks[t];
+            let mut name = render_name(&task.name, query, theme);
+            match view {
+                View::List(_) => {}
+                // Tasks come from every list, so say where each lives
+                View::Bucket(_) | View::Agenda => name.0.push(Span::styled(
+                    format!(" ({})", lists[l].name),
+                    Style::default().fg(theme.muted),
+                )),
+                View::Completed => name.0.push(Span::styled(
+                    format!(
+                        " ({}, done {})",
+                        lists[l].name,
+                        task.completed_at
+                            .expect("only completed tasks are shown")
+                            .with_timezone(&Local)
+                            .format(date_format)
+                    ),
+                    Style::default().fg(theme.muted),
+                )),
+            }
+            let untouched = now - task.last_edited();
+            let stale = !task.is_completed() && stale_after.is_some_and(|after| untouched > after);
+            if stale {
+                name.0.push(Span::styled(
+                    format!(" (untouched {})", humanize_duration(untouched)),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            if let Some(recurrence) = task.recurrence {
+                name.0.push(Span::styled(
+                    format!(" ↻ {}", recurrence.name()),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            if let Some((done, total)) = task.subtask_progress() {
+                name.0.push(Span::styled(
+                    format!(" {}/{}", done, total),
+                    Style::default().fg(if done == total {
+                        theme.done
+                    } else {
+                        theme.muted
+                    }),
+                ));
+            }
+            if !task.comments.is_empty() {
+                name.0.push(Span::styled(
+                    format!(" [{}]", task.comments.len()),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            if marked.is_some_and(|marked| marked.contains(&(l, t))) {
+                name.0
+                    .insert(0, Span::styled("* ", Style::default().fg(theme.marker)));
+            }
+            if task.running_since.is_some() {
+                name.0
+                    .insert(0, Span::styled("▶ ", Style::default().fg(theme.done)));
+            }
+            let cells = [
+                Cell::from(name),
+                Cell::from(Span::styled(
+                    task.status.name(),
+                    Style::default().fg(status_color(task.status, theme)),
+                )),
+                Cell::from(Span::styled(
+                    task.priority.name(),
+                    Style::default().fg(priority_color(task.priority, theme)),
+                )),
                 Cell::from(Span::raw(format!("{:?}", task.tags))),
-                Cell::from(Span::raw(format!("{}", task.start_date.format("%D %T")))),
-                Cell::from(Span::raw(format!("{}", task.due_date.format("%D %T")))),
-            ])
+                Cell::from(Span::raw(format!(
+                    "{}",
+                    task.start_date.with_timezone(&Local).format(date_format)
+                ))),
+                Cell::from(Span::styled(
+                    if app.countdown && !task.is_completed() {
+                        countdown(task.due_date - now)
+                    } else {
+                        task.due_date
+                            .with_timezone(&Local)
+                            .format(date_format)
+                            .to_string()
+                    },
+                    // Done tasks aren't urgent, they keep the row's gray
+                    if task.is_completed() {
+                        Style::default()
+                    } else {
+                        Style::default().fg(due_color(app, task.due_date, now))
+                    },
+                )),
+                Cell::from(if task.due_before_start() {
+                    Span::styled("before start", Style::default().fg(theme.error))
+                } else {
+                    Span::raw(
+                        // Left empty for tasks that start and end at once, like
+                        // ones made with both dates set to now
+                        if (task.due_date - task.start_date).num_minutes() == 0 {
+                            String::new()
+                        } else {
+                            humanize_duration(task.due_date - task.start_date)
+                        },
+                    )
+                }),
+                Cell::from(Span::raw(format_duration(task.elapsed()))),
+            ];
+            let row = Row::new(shown.iter().map(|&c| cells[c].clone()).collect::<Vec<_>>());
+            if task.is_completed() {
+                row.style(
+                    Style::default()
+                        .fg(theme.muted)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                )
+            } else if lists[l].is_blocked(task) {
+                row.style(Style::default().fg(theme.muted))
+            } else {
+                // Ticks redraw every second, so rows change color as their
+                // due dates come up
+                let left = task.due_date - now;
+                let style = if left < chrono::Duration::zero() {
+                    Style::default().fg(theme.error)
+                } else if left < near {
+                    Style::default().fg(theme.due_near)
+                } else {
+                    match task.priority {
+                        Priority::Urgent => Style::default().fg(theme.error),
+                        Priority::High => Style::default().fg(theme.active),
+                        Priority::Low | Priority::Medium => Style::default(),
+                    }
+                };
+                if stale {
+                    row.style(style.add_modifier(Modifier::ITALIC))
+                } else {
+                    row.style(style)
+                }
+            }
         })
         .collect();
+    if view == View::Agenda {
+        let mut rows = tasks.into_iter();
+        tasks = agenda_lines(app, visible)
+            .into_iter()
+            .map(|line| match line {
+                AgendaLine::Task(_) => rows.next().expect("a row for every task line"),
+                header => Row::new(vec![Cell::from(Span::styled(
+                    agenda_header(header),
+                    Style::default()
+                        .fg(theme.marker)
+                        .add_modifier(Modifier::BOLD),
+                ))]),
+            })
+            .collect();
+    }
 
-    let table = ["Name", "Tags", "Start Date", "Due Date"];
-
-    let table = table
+    let table = shown
         .iter()
-        .map(|t| {
+        .map(|&c| {
+            let t = TASK_COLUMNS[c].0;
             Cell::from(Span::styled(
                 t.to_owned(),
                 Style::default().add_modifier(Modifier::BOLD),
@@ -205,27 +844,607 @@ fn render_tasks<'a>(list: &TaskList) -> Table<'a> {
         })
         .collect::<Vec<Cell>>();
 
+    // The status line still names the view in dense mode
+    let block = if app.dense {
+        Block::default()
+    } else {
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(match &app.tag_filter {
+                // Tasks without the tags are hidden, so say which ones
+                Some(filter) => format!(
+                    "{} [{}]",
+                    view_title(app, view),
+                    filter
+                        .tags
+                        .iter()
+                        .map(|tag| format!("#{}", tag))
+                        .collect::<Vec<_>>()
+                        .join(if filter.all { " AND " } else { " OR " })
+                ),
+                None => view_title(app, view),
+            })
+            .border_type(BorderType::Plain)
+    };
     let table = Table::new(tasks)
         .header(Row::new(table))
+        .block(block)
+        .widths(widths)
+        .highlight_style(
+            Style::default()
+                .bg(theme.selected_bg)
+                .fg(theme.selected_fg)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    table
+}
+
+/// How many tag suggestions fit in the popup
+const TAG_SUGGESTIONS: usize = 5;
+
+/// The tags offered while typing into a tag prompt, the one Tab picks first.
+fn render_suggestions<'a>(suggestions: &[String], theme: &Theme) -> List<'a> {
+    let items: Vec<ListItem> = suggestions
+        .iter()
+        .take(TAG_SUGGESTIONS)
+        .enumerate()
+        .map(|(i, tag)| {
+            let style = if i == 0 {
+                Style::default()
+                    .fg(theme.marker)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Span::styled(tag.clone(), style))
+        })
+        .collect();
+    List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title("Tags (tab)")
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// The new task form, one field per line with the focused one highlighted.
+fn render_form<'a>(form: &TaskForm, list: &str, theme: &Theme) -> Paragraph<'a> {
+    let lines: Vec<Spans> = FORM_FIELDS
+        .iter()
+        .zip(&form.fields)
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let (style, cursor) = if i == form.focused {
+                (
+                    Style::default()
+                        .fg(theme.active)
+                        .add_modifier(Modifier::BOLD),
+                    "_",
+                )
+            } else {
+                (Style::default(), "")
+            };
+            Spans::from(vec![
+                Span::styled(format!("{:<7}", label), style),
+                Span::raw(format!("{}{}", value, cursor)),
+            ])
+        })
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(if form.editing.is_some() {
+                "Edit task".to_string()
+            } else {
+                format!("New task in {}", list)
+            })
+            .border_type(BorderType::Plain),
+    )
+}
+
+fn view_title(app: &App, view: View) -> String {
+    match view {
+        View::List(list) => app.tasklists[list].name.clone(),
+        View::Bucket(bucket) => bucket.name().to_string(),
+        View::Completed | View::Agenda => app.view_name().to_string(),
+    }
+}
+
+/// The tags in use with the picked ones checked, highlighting the selected
+/// one.
+fn render_tag_picker<'a>(picker: &TagPicker, theme: &Theme) -> Paragraph<'a> {
+    let lines: Vec<Spans> = picker
+        .tags
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| {
+            let picked = picker.chosen.iter().any(|t| t.eq_ignore_ascii_case(tag));
+            let style = if i == picker.selected {
+                Style::default()
+                    .fg(theme.active)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let check = if picked { "[x]" } else { "[ ]" };
+            Spans::from(Span::styled(format!("{} #{}", check, tag), style))
+        })
+        .collect();
+    Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .title(list.name.to_owned())
+                .style(Style::default().fg(theme.text))
+                .title("Filter by tags")
                 .border_type(BorderType::Plain),
         )
-        .widths(&[
-            Constraint::Percentage(30),
-            Constraint::Percentage(30),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
+        .scroll((
+            (picker.selected + 1).saturating_sub(TAG_PICKER_ROWS) as u16,
+            0,
+        ))
+}
+
+/// The search across lists: the query, then the results around the
+/// highlighted one with the matched characters picked out.
+fn render_finder<'a>(app: &App, finder: &Finder) -> Paragraph<'a> {
+    let results = finder.results(&app.tasklists);
+    let mut lines = vec![Spans::from(format!("/{}_", finder.query))];
+    let first = (finder.selected + 1).saturating_sub(FINDER_RESULTS);
+    for (i, &(l, t)) in results.iter().enumerate().skip(first).take(FINDER_RESULTS) {
+        let task = app.task((l, t));
+        let mut line = render_name(&task.name, Some(&finder.query), &app.theme);
+        line.0.insert(
+            0,
+            if i == finder.selected {
+                Span::styled(
+                    "> ",
+                    Style::default()
+                        .fg(app.theme.active)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::raw("  ")
+            },
+        );
+        let gray = Style::default().fg(app.theme.muted);
+        for tag in &task.tags {
+            line.0.push(Span::styled(format!(" #{}", tag), gray));
+        }
+        line.0
+            .push(Span::styled(format!(" ({})", app.tasklists[l].name), gray));
+        lines.push(line);
+    }
+    if results.is_empty() && !finder.query.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "No matches",
+            Style::default().fg(app.theme.muted),
+        )));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(app.theme.text))
+            .title(format!("Search all lists ({})", results.len()))
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// Lists a task's subtasks with boxes to check, highlighting `selected`.
+fn render_checklist<'a>(task: &Task, selected: usize, theme: &Theme) -> Paragraph<'a> {
+    let mut lines: Vec<Spans> = task
+        .subtasks
+        .iter()
+        .enumerate()
+        .map(|(i, subtask)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(theme.active)
+                    .add_modifier(Modifier::BOLD)
+            } else if subtask.done {
+                Style::default().fg(theme.muted)
+            } else {
+                Style::default()
+            };
+            let check = if subtask.done { "[x]" } else { "[ ]" };
+            Spans::from(Span::styled(format!("{} {}", check, subtask.name), style))
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "No subtasks yet",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    let title = match task.subtask_progress() {
+        Some((done, total)) => format!("Subtasks of {} ({}/{})", task.name, done, total),
+        None => format!("Subtasks of {}", task.name),
+    };
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// Shows everything about a task, which the table cuts short, with its
+/// subtasks and comments below.
+fn render_details<'a>(app: &App, (list, task): TaskRef) -> Paragraph<'a> {
+    let theme = &app.theme;
+    let date_format = &app.config.date_format;
+    let list = &app.tasklists[list];
+    let task = &list.tasks[task];
+    let date = |date: chrono::DateTime<chrono::Utc>| {
+        date.with_timezone(&Local).format(date_format).to_string()
+    };
+    let field = |label: &str, value: String| {
+        Spans::from(vec![
+            Span::styled(format!("{:<10}", label), Style::default().fg(theme.muted)),
+            Span::raw(value),
         ])
-        .highlight_style(
+    };
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            task.name.clone(),
             Style::default()
-                .bg(Color::Red)
-                .fg(Color::Black)
+                .fg(theme.active)
                 .add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(""),
+        field("List", list.name.clone()),
+        field("Status", task.status.name().to_string()),
+        field("Priority", task.priority.name().to_string()),
+        field(
+            "Tags",
+            if task.tags.is_empty() {
+                "none".to_string()
+            } else {
+                task.tags.join(", ")
+            },
+        ),
+        field("Start", date(task.start_date)),
+        field(
+            "Due",
+            format!(
+                "{} ({})",
+                date(task.due_date),
+                countdown(task.due_date - chrono::Utc::now())
+            ),
+        ),
+    ];
+    if let Some(done) = task.completed_at {
+        lines.push(field("Done", date(done)));
+    }
+    if let Some(recurrence) = task.recurrence {
+        lines.push(field("Repeats", recurrence.name()));
+    }
+    if let Some(lead) = task.reminder_lead {
+        let lead = humanize_duration(chrono::Duration::seconds(lead));
+        lines.push(field("Reminder", format!("{} before", lead)));
+    }
+    if let Some(estimate) = task.estimate {
+        lines.push(field(
+            "Estimate",
+            format_duration(chrono::Duration::seconds(estimate)),
+        ));
+    }
+    if task.elapsed() > chrono::Duration::zero() {
+        lines.push(field("Spent", format_duration(task.elapsed())));
+    }
+    let blockers: Vec<&str> = task
+        .depends_on
+        .iter()
+        .filter_map(|&id| list.tasks.iter().find(|t| t.id == id))
+        .map(|t| t.name.as_str())
+        .collect();
+    if !blockers.is_empty() {
+        lines.push(field("After", blockers.join(", ")));
+    }
+    lines.push(field("Edited", date(task.last_edited())));
+    if !task.subtasks.is_empty() {
+        lines.push(Spans::from(""));
+        for subtask in &task.subtasks {
+            let check = if subtask.done { "[x]" } else { "[ ]" };
+            lines.push(Spans::from(format!("{} {}", check, subtask.name)));
+        }
+    }
+    if !task.comments.is_empty() {
+        lines.push(Spans::from(""));
+    }
+    for comment in &task.comments {
+        lines.push(Spans::from(vec![
+            Span::styled(
+                format!("{}  ", date(comment.at)),
+                Style::default().fg(theme.muted),
+            ),
+            Span::raw(comment.text.clone()),
+        ]));
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .title("Details")
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// Shows just the next task to work on, centered in a panel `height` rows tall.
+fn render_focus<'a>(app: &App, height: u16) -> Paragraph<'a> {
+    let mut lines = match app.next_action() {
+        Some(next) => {
+            let task = app.task(next);
+            let mut lines = vec![
+                Spans::from(Span::styled(
+                    app.tasklists[next.0].name.clone(),
+                    Style::default().fg(app.theme.muted),
+                )),
+                Spans::from(""),
+                Spans::from(Span::styled(
+                    task.name.clone(),
+                    Style::default()
+                        .fg(app.theme.active)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(""),
+                Spans::from(format!(
+                    "due {}",
+                    task.due_date
+                        .with_timezone(&Local)
+                        .format(&app.config.date_format)
+                )),
+            ];
+            if !task.comments.is_empty() {
+                lines.push(Spans::from(""));
+            }
+            // Newest first, as many as fit
+            for comment in task.comments.iter().rev().take(FOCUS_COMMENTS) {
+                lines.push(Spans::from(vec![
+                    Span::styled(
+                        format!(
+                            "{}  ",
+                            comment
+                                .at
+                                .with_timezone(&Local)
+                                .format(&app.config.date_format)
+                        ),
+                        Style::default().fg(app.theme.muted),
+                    ),
+                    Span::raw(comment.text.clone()),
+                ]));
+            }
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled(
+                "x: done, f: back",
+                Style::default().fg(app.theme.muted),
+            )));
+            lines
+        }
+        None => vec![Spans::from("Nothing left to do")],
+    };
+    // Pad the top so the text sits in the middle, leaving room for the borders
+    let padding = height.saturating_sub(2 + lines.len() as u16) / 2;
+    for _ in 0..padding {
+        lines.insert(0, Spans::from(""));
+    }
+    Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(app.theme.text))
+            .title("Focus")
+            .border_type(BorderType::Plain),
+    )
+}
+
+fn render_status<'a>(app: &App) -> Paragraph<'a> {
+    Paragraph::new(status_line(app))
+}
+
+/// The counts for the view and the filters on it, or the marked tasks in
+/// visual mode.
+fn status_line(app: &App) -> String {
+    if let Some(marked) = &app.marked {
+        return format!(
+            "-- VISUAL -- {} marked (space: mark, d: delete, m: move, t: tag)",
+            marked.len()
         );
+    }
+    let tasks: Vec<&Task> = match app.view() {
+        View::List(list) => app.tasklists[list].tasks.iter().collect(),
+        View::Bucket(_) | View::Completed | View::Agenda => {
+            app.visible_tasks().iter().map(|&r| app.task(r)).collect()
+        }
+    };
+    let estimate = tasks
+        .iter()
+        .filter(|task| !task.is_completed())
+        .filter_map(|task| task.estimate)
+        .map(chrono::Duration::seconds)
+        .fold(chrono::Duration::zero(), |total, estimate| total + estimate);
+    let mut status = format!(
+        "{}{}: {} tasks, {} estimated",
+        app.view_name(),
+        if app.dirty { " [+]" } else { "" },
+        tasks.len(),
+        format_duration(estimate)
+    );
+    let now = Local::now();
+    let open = tasks.iter().filter(|task| !task.is_completed()).count();
+    let overdue = tasks
+        .iter()
+        .filter(|task| !task.is_completed() && Bucket::of(task.due_date, now) == Bucket::Overdue)
+        .count();
+    if overdue > 0 {
+        status.push_str(&format!(", {}% overdue", overdue * 100 / open));
+    }
+    if let Some(day) = app.day_filter {
+        status.push_str(&format!(" | due {} (esc to clear)", day));
+    }
+    if let Some(filter) = &app.tag_filter {
+        let mode = if filter.all { " AND " } else { " OR " };
+        status.push_str(&format!(
+            " | tags {} (&: toggle AND/OR, esc to clear)",
+            filter.tags.join(mode)
+        ));
+    }
+    if let Some(filter) = app.status_filter {
+        status.push_str(&format!(" | {} (esc to clear)", filter.name()));
+    }
+    if app.hide_completed {
+        status.push_str(" | completed hidden");
+    }
+    status
+}
 
-    table
+/// The most common keys for whatever has focus, as bound in the keymap.
+fn render_hints<'a>(app: &App) -> Paragraph<'a> {
+    let hints: &[(&str, Option<Action>)] = match app.mode() {
+        Mode::Editing if !app.tag_suggestions().is_empty() => {
+            &[("enter", None), ("esc", None), ("tab", None)]
+        }
+        Mode::Search | Mode::Editing | Mode::Find => &[("enter", None), ("esc", None)],
+        Mode::Confirm => &[("y", None), ("n", None)],
+        Mode::Calendar => &[
+            ("last month", Some(Action::ScrollLeft)),
+            ("next month", Some(Action::ScrollRight)),
+            ("enter", None),
+            ("esc", None),
+        ],
+        Mode::Form => &[("tab", None), ("enter", None), ("esc", None)],
+        Mode::TagPicker => &[("pick", Some(Action::Mark)), ("enter", None), ("esc", None)],
+        Mode::Checklist => &[
+            ("add", Some(Action::Add)),
+            ("check", Some(Action::Complete)),
+            ("delete", Some(Action::Delete)),
+            ("esc", None),
+        ],
+        Mode::Focus => &[("done", Some(Action::Complete)), ("esc", None)],
+        Mode::Board => &[
+            ("move back", Some(Action::ScrollLeft)),
+            ("move on", Some(Action::ScrollRight)),
+            ("enter", None),
+            ("esc", None),
+        ],
+        Mode::Visual => &[
+            ("mark", Some(Action::Mark)),
+            ("delete", Some(Action::Delete)),
+            ("move", Some(Action::Move)),
+            ("tag", Some(Action::Tag)),
+            ("esc", None),
+        ],
+        Mode::Normal if app.task_state.selected().is_some() => &[
+            ("back", Some(Action::Left)),
+            ("done", Some(Action::Complete)),
+            ("status", Some(Action::Status)),
+            ("snooze", Some(Action::Snooze)),
+            ("delete", Some(Action::Delete)),
+            ("visual", Some(Action::Visual)),
+            ("search", Some(Action::Search)),
+        ],
+        Mode::Normal => &[
+            ("open", Some(Action::Right)),
+            ("new list", Some(Action::NewListBelow)),
+            ("go to", Some(Action::GoToList)),
+            ("agenda", Some(Action::Agenda)),
+            ("capture", Some(Action::Capture)),
+            ("command", Some(Action::Command)),
+            ("quit", Some(Action::Quit)),
+        ],
+    };
+    let key = Style::default().fg(app.theme.marker);
+    let mut spans = Vec::new();
+    for &(label, action) in hints {
+        // Prompts take fixed keys, so their label is the key itself
+        let (name, label) = match action {
+            Some(action) => match app.keymap.key(action) {
+                Some(name) => (name, label),
+                None => continue,
+            },
+            None => (label.to_string(), prompt_hint(label, app.mode())),
+        };
+        spans.push(Span::styled(name, key));
+        spans.push(Span::raw(format!(" {}  ", label)));
+    }
+    if let Some(name) = app.keymap.key(Action::Hints) {
+        spans.push(Span::styled(name, key));
+        spans.push(Span::raw(" hide hints"));
+    }
+    Paragraph::new(Spans::from(spans)).style(Style::default().fg(app.theme.muted))
+}
+
+fn prompt_hint(key: &str, mode: Mode) -> &'static str {
+    match key {
+        "enter" if mode == Mode::Find => "go to task",
+        "enter" if mode == Mode::TagPicker => "filter",
+        "enter" => "ok",
+        "esc" if mode == Mode::Checklist => "close",
+        "esc" => "cancel",
+        "tab" if mode == Mode::Form => "next field",
+        "tab" => "complete tag",
+        "y" => "yes",
+        _ => "no",
+    }
+}
+
+/// Splits a task name into spans, styling the characters matched by `query`.
+fn render_name<'a>(name: &str, query: Option<&str>, theme: &Theme) -> Spans<'a> {
+    let matched = match query.and_then(|query| fuzzy_match(query, name)) {
+        Some((_, matched)) => matched,
+        None => return Spans::from(Span::raw(name.to_owned())),
+    };
+
+    let highlight = Style::default()
+        .fg(theme.active)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in name.chars().enumerate() {
+        let is_matched = matched.contains(&i);
+        if is_matched != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                highlight
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    let style = if run_matched {
+        highlight
+    } else {
+        Style::default()
+    };
+    spans.push(Span::styled(run, style));
+    Spans::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::{app, list, press};
+
+    #[test]
+    fn status_line_only_estimates_open_tasks() {
+        let mut app = app(vec![list(0, "One", &["a", "b"])]);
+        app.tasklists[0].tasks[0].estimate = Some(2 * 3600);
+        app.tasklists[0].tasks[1].estimate = Some(30 * 60);
+        press(&mut app, "l");
+        assert!(status_line(&app).contains(&format!(
+            "2 tasks, {} estimated",
+            format_duration(chrono::Duration::minutes(150))
+        )));
+        press(&mut app, " ");
+        assert!(status_line(&app).contains(&format!(
+            "2 tasks, {} estimated",
+            format_duration(chrono::Duration::minutes(30))
+        )));
+    }
 }
@@ -0,0 +1,41 @@
+use std::env;
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier},
+    widgets::Widget,
+};
+
+use crate::theme::Theme;
+
+/// Whether colors should be left out, because `NO_COLOR` is set or the
+/// terminal can't show them.
+pub fn colors_disabled() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+        || env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+/// Drawn over everything else to swap colors for text attributes:
+/// highlighted backgrounds become reverse video, the theme's error and badge
+/// colors bold and its muted color dim.
+pub struct Monochrome<'a>(pub &'a Theme);
+
+impl Widget for Monochrome<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if cell.bg != Color::Reset {
+                    cell.modifier |= Modifier::REVERSED;
+                }
+                if cell.fg == self.0.error || cell.fg == self.0.badge {
+                    cell.modifier |= Modifier::BOLD;
+                } else if cell.fg == self.0.muted {
+                    cell.modifier |= Modifier::DIM;
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use chrono::prelude::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone};
+use chrono::{Duration, Months};
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// The index in `names` of the one `word` spells or starts with, given at
+/// least three letters, like "fri" or "sept".
+fn name_index(names: &[&str], word: &str) -> Option<usize> {
+    if word.len() < 3 {
+        return None;
+    }
+    names.iter().position(|name| name.starts_with(word))
+}
+
+/// Reads "3", "an" or "a" as a count.
+fn count(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" => Some(1),
+        _ => word.parse().ok(),
+    }
+}
+
+/// A length of time in words, like "3 days" or "an hour".
+enum Amount {
+    Minutes(i64),
+    Days(i64),
+    Months(u32),
+}
+
+fn amount(words: &[&str]) -> Option<Amount> {
+    let n = count(words.first()?)?;
+    let unit = words.get(1)?.trim_end_matches('s');
+    Some(match unit {
+        "min" | "minute" => Amount::Minutes(n.into()),
+        "hr" | "hour" => Amount::Minutes(i64::from(n) * 60),
+        "day" => Amount::Days(n.into()),
+        "week" => Amount::Days(i64::from(n) * 7),
+        "month" => Amount::Months(n),
+        "year" => Amount::Months(n.checked_mul(12)?),
+        _ => return None,
+    })
+}
+
+/// Reads a day of the month like "5" or "5th".
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))
+        .unwrap_or(word);
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// Reads a month and day in either order, like "march 5" or "5th mar",
+/// with an optional year after. Without one it's the next time that day
+/// comes around.
+fn month_day(words: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let (month, day) = match (name_index(&MONTHS, words.first()?), words.get(1)) {
+        (Some(month), Some(day)) => (month, day_of_month(day)?),
+        (None, Some(month)) => (name_index(&MONTHS, month)?, day_of_month(words[0])?),
+        _ => return None,
+    };
+    let month = month as u32 + 1;
+    if let Some(year) = words.get(2).and_then(|year| year.parse::<i32>().ok()) {
+        return Some((NaiveDate::from_ymd_opt(year, month, day)?, 3));
+    }
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date < today {
+        Some((NaiveDate::from_ymd_opt(today.year() + 1, month, day)?, 2))
+    } else {
+        Some((date, 2))
+    }
+}
+
+/// Reads a day at the start of `words`, returning it and how many words it
+/// took.
+fn day(words: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let first = *words.first()?;
+    let weekday = |word: &str| {
+        let weekday = name_index(&WEEKDAYS, word)? as i64;
+        let ahead = (weekday - i64::from(today.weekday().num_days_from_monday())).rem_euclid(7);
+        // Never today, which has its own word
+        Some(today + Duration::days(if ahead == 0 { 7 } else { ahead }))
+    };
+    match first {
+        "today" => return Some((today, 1)),
+        "tomorrow" => return Some((today + Duration::days(1), 1)),
+        "next" => {
+            let date = match *words.get(1)? {
+                "week" => today + Duration::days(7),
+                "month" => today.checked_add_months(Months::new(1))?,
+                "year" => today.checked_add_months(Months::new(12))?,
+                word => weekday(word)?,
+            };
+            return Some((date, 2));
+        }
+        "in" => {
+            let date = match amount(&words[1..])? {
+                Amount::Days(days) => today + Duration::days(days),
+                Amount::Months(months) => today.checked_add_months(Months::new(months))?,
+                Amount::Minutes(_) => return None,
+            };
+            return Some((date, 3));
+        }
+        _ => {}
+    }
+    if let Some(date) = weekday(first) {
+        return Some((date, 1));
+    }
+    month_day(words, today)
+}
+
+/// Reads a time at the start of `words` like "5pm", "5:30 pm", "17:00",
+/// "noon" or "midnight", returning it and how many words it took. Bare
+/// numbers are left alone as they could be days.
+fn time(words: &[&str]) -> Option<(NaiveTime, usize)> {
+    let first = *words.first()?;
+    match first {
+        "noon" => return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1)),
+        "midnight" => return Some((NaiveTime::from_hms_opt(0, 0, 0)?, 1)),
+        _ => {}
+    }
+    let (clock, meridiem, taken) = if let Some(clock) = first.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = first.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else {
+        match words.get(1).copied() {
+            Some("am") => (first, Some(false), 2),
+            Some("pm") => (first, Some(true), 2),
+            _ => (first, None, 1),
+        }
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse().ok()?),
+        None if meridiem.is_some() => (clock.parse().ok()?, 0),
+        None => return None,
+    };
+    let hour = match meridiem {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => hour,
+    };
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, taken))
+}
+
+/// Reads a date written out in words, like "tomorrow 5pm", "next friday",
+/// "in 3 days", "march 5 at noon" or "in 2 hours". Days without a time keep
+/// the time of `now`, and a time without a day is the next time it comes
+/// around.
+pub fn parse_natural_date(input: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let input = input.to_lowercase();
+    let words: Vec<&str> = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect();
+    match words.as_slice() {
+        [] => return None,
+        ["now"] => return Some(now),
+        ["in", rest @ ..] if rest.len() == 2 => {
+            if let Some(Amount::Minutes(minutes)) = amount(rest) {
+                return Some(now + Duration::minutes(minutes));
+            }
+        }
+        _ => {}
+    }
+    let today = now.date_naive();
+    let (mut date, mut at) = (None, None);
+    let mut rest = &words[..];
+    while let Some(&word) = rest.first() {
+        if word == "at" || word == "on" {
+            rest = &rest[1..];
+        } else if let Some((day, taken)) = date.is_none().then(|| day(rest, today)).flatten() {
+            date = Some(day);
+            rest = &rest[taken..];
+        } else if let Some((time, taken)) = at.is_none().then(|| time(rest)).flatten() {
+            at = Some(time);
+            rest = &rest[taken..];
+        } else {
+            return None;
+        }
+    }
+    let at_time = |date: NaiveDate, time: NaiveTime| {
+        Local.from_local_datetime(&date.and_time(time)).earliest()
+    };
+    match (date, at) {
+        (Some(date), time) => at_time(date, time.unwrap_or_else(|| now.time())),
+        (None, Some(time)) => {
+            let next = at_time(today, time)?;
+            if next < now {
+                at_time(today + Duration::days(1), time)
+            } else {
+                Some(next)
+            }
+        }
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `input` as if it were typed on Wednesday 2026-03-04 at 10:30.
+    fn natural(input: &str) -> Option<String> {
+        let now = Local.with_ymd_and_hms(2026, 3, 4, 10, 30, 0).unwrap();
+        let date = parse_natural_date(input, now)?;
+        Some(date.format("%a %Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn natural_dates_count_from_now() {
+        let read = |input| natural(input).unwrap();
+        assert_eq!(read("now"), "Wed 2026-03-04 10:30");
+        assert_eq!(read("tomorrow"), "Thu 2026-03-05 10:30");
+        assert_eq!(read("Tomorrow 5pm"), "Thu 2026-03-05 17:00");
+        assert_eq!(read("today at 5:15 pm"), "Wed 2026-03-04 17:15");
+        assert_eq!(read("friday"), "Fri 2026-03-06 10:30");
+        assert_eq!(read("next fri at noon"), "Fri 2026-03-06 12:00");
+        assert_eq!(read("wednesday"), "Wed 2026-03-11 10:30");
+        assert_eq!(read("next week"), "Wed 2026-03-11 10:30");
+        assert_eq!(read("next month"), "Sat 2026-04-04 10:30");
+        assert_eq!(read("in 3 days"), "Sat 2026-03-07 10:30");
+        assert_eq!(read("in a week 9am"), "Wed 2026-03-11 09:00");
+        assert_eq!(read("in 2 hours"), "Wed 2026-03-04 12:30");
+        assert_eq!(read("in 45 mins"), "Wed 2026-03-04 11:15");
+        // Times that have passed today are tomorrow's
+        assert_eq!(read("9am"), "Thu 2026-03-05 09:00");
+        assert_eq!(read("17:45"), "Wed 2026-03-04 17:45");
+    }
+
+    #[test]
+    fn natural_dates_name_days_of_the_year() {
+        let read = |input| natural(input).unwrap();
+        assert_eq!(read("march 20"), "Fri 2026-03-20 10:30");
+        assert_eq!(read("20th Mar at midnight"), "Fri 2026-03-20 00:00");
+        assert_eq!(read("jan 5"), "Tue 2027-01-05 10:30");
+        assert_eq!(read("Sept 1st, 2030 8:00"), "Sun 2030-09-01 08:00");
+        for nonsense in [
+            "",
+            "soon",
+            "5",
+            "13pm",
+            "feb 30",
+            "tomorrow tomorrow",
+            "in 2",
+        ] {
+            assert_eq!(natural(nonsense), None, "{}", nonsense);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::prelude::Utc;
+use chrono::Duration;
+use notify_rust::Notification;
+
+use crate::db::TaskList;
+
+/// Fires a desktop notification `lead` before each task's due date, or the
+/// task's own lead if it has one. Reminders that came due while tbg was closed
+/// fire on the first check. Each task records the due date it was reminded
+/// for, so a reminder fires once until the task is snoozed. Returns the
+/// indices of the lists whose tasks were reminded.
+pub fn check_reminders(lists: &mut [TaskList], lead: Duration) -> Vec<usize> {
+    let now = Utc::now();
+    let mut changed = Vec::new();
+    for (index, list) in lists.iter_mut().enumerate() {
+        for task in &mut list.tasks {
+            let lead = task.reminder_lead.map_or(lead, Duration::seconds);
+            if task.is_completed()
+                || task.due_date - lead > now
+                || task.reminded_for == Some(task.due_date)
+            {
+                continue;
+            }
+            task.reminded_for = Some(task.due_date);
+            if !changed.contains(&index) {
+                changed.push(index);
+            }
+            let summary = if task.due_date > now {
+                format!("Due soon: {}", task.name)
+            } else {
+                format!("Due: {}", task.name)
+            };
+            // A missing notification daemon shouldn't take down the UI
+            let _ = Notification::new()
+                .summary(&summary)
+                .body(&list.name)
+                .appname("tbg")
+                .show();
+        }
+    }
+    changed
+}
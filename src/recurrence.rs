@@ -0,0 +1,140 @@
+use chrono::prelude::{DateTime, Local, TimeZone, Utc};
+use chrono::{Duration, Months};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Status, Task, TaskList};
+use crate::duration::parse_duration;
+
+/// How often a task comes back once it's done.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    /// Every so many seconds, like every 3 days
+    Every(i64),
+}
+
+impl Recurrence {
+    /// Reads "daily", "weekly", "monthly", or a duration like "every 3d" or
+    /// just "3d".
+    pub fn parse(input: &str) -> Option<Recurrence> {
+        let input = input.trim();
+        match input {
+            "daily" => Some(Recurrence::Daily),
+            "weekly" => Some(Recurrence::Weekly),
+            "monthly" => Some(Recurrence::Monthly),
+            _ => {
+                let every = input.strip_prefix("every").unwrap_or(input);
+                let period = parse_duration(every)?;
+                (period > Duration::zero()).then(|| Recurrence::Every(period.num_seconds()))
+            }
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly => "weekly".to_string(),
+            Recurrence::Monthly => "monthly".to_string(),
+            Recurrence::Every(seconds) => format!("every {}", short_duration(seconds)),
+        }
+    }
+
+    /// `date` moved on by `times` repetitions. It's counted in local time, so
+    /// a daily task keeps its time of day when the clocks change.
+    fn shift(self, date: DateTime<Utc>, times: u32) -> DateTime<Utc> {
+        let local = date.with_timezone(&Local).naive_local();
+        let shifted = match self {
+            Recurrence::Daily => Some(local + Duration::days(times.into())),
+            Recurrence::Weekly => Some(local + Duration::weeks(times.into())),
+            Recurrence::Monthly => local.checked_add_months(Months::new(times)),
+            Recurrence::Every(seconds) => {
+                Some(local + Duration::seconds(seconds * i64::from(times)))
+            }
+        };
+        shifted
+            .and_then(|shifted| Local.from_local_datetime(&shifted).earliest())
+            .map_or(date, |shifted| shifted.with_timezone(&Utc))
+    }
+
+    /// How many repetitions after `due` the next instance falls, skipping
+    /// the ones that would already be overdue at `now`.
+    fn repetitions(self, due: DateTime<Utc>, now: DateTime<Utc>) -> u32 {
+        let period = match self {
+            Recurrence::Daily => Duration::days(1),
+            Recurrence::Weekly => Duration::weeks(1),
+            Recurrence::Monthly => Duration::days(28),
+            Recurrence::Every(seconds) => Duration::seconds(seconds),
+        };
+        // A guess from the average length, put right by stepping after
+        let mut times = if due < now {
+            ((now - due).num_seconds() / period.num_seconds()).max(1) as u32
+        } else {
+            1
+        };
+        while times > 1 && self.shift(due, times - 1) > now {
+            times -= 1;
+        }
+        while self.shift(due, times) <= now {
+            times += 1;
+        }
+        times
+    }
+}
+
+/// Writes a number of seconds as a duration `parse_duration` reads back, in
+/// the largest unit that divides it.
+fn short_duration(seconds: i64) -> String {
+    [(604800, "w"), (86400, "d"), (3600, "h"), (60, "m")]
+        .iter()
+        .find(|&&(unit, _)| seconds % unit == 0)
+        .map_or(format!("{}s", seconds), |&(unit, suffix)| {
+            format!("{}{}", seconds / unit, suffix)
+        })
+}
+
+/// The next instance of a recurring task that was just done: still to do,
+/// with its dates moved on to the first repetition that isn't overdue.
+fn next_instance(task: &Task, id: usize, recurrence: Recurrence, now: DateTime<Utc>) -> Task {
+    let times = recurrence.repetitions(task.due_date, now);
+    let mut next = task.clone();
+    next.id = id;
+    next.start_date = recurrence.shift(task.start_date, times);
+    next.due_date = recurrence.shift(task.due_date, times);
+    next.completed_at = None;
+    next.status = Status::Todo;
+    next.time_spent = 0;
+    next.running_since = None;
+    next.reminded_for = None;
+    next.updated_at = None;
+    next.comments.clear();
+    for subtask in &mut next.subtasks {
+        subtask.done = false;
+    }
+    next
+}
+
+/// Adds the next instance after each completed task in `list` that recurs.
+/// The rule is handed on to the new instance, so every task only comes back
+/// once. Returns whether anything was added.
+pub fn spawn_recurring(list: &mut TaskList, now: DateTime<Utc>) -> bool {
+    let mut spawned = false;
+    let mut index = 0;
+    while index < list.tasks.len() {
+        let task = &list.tasks[index];
+        match task.recurrence {
+            Some(recurrence) if task.is_completed() => {
+                let id = list.tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+                let next = next_instance(task, id, recurrence, now);
+                list.tasks[index].recurrence = None;
+                list.tasks.insert(index + 1, next);
+                spawned = true;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    spawned
+}
@@ -0,0 +1,126 @@
+use crate::app::TaskRef;
+use crate::db::TaskList;
+
+/// Scores `text` against `pattern` as a case-insensitive subsequence, fzf
+/// style. Returns `None` if not every pattern character appears in order,
+/// otherwise the score and the char indices of `text` that matched.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let chars: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let mut score = 0;
+    let mut matched = Vec::with_capacity(pattern.len());
+    let mut p = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if p == pattern.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(pattern[p].to_lowercase()) {
+            continue;
+        }
+        score += 1;
+        // Consecutive matches and matches at the start of a word are worth more
+        if matched.last().is_some_and(|&last| last + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || !chars[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        matched.push(i);
+        p += 1;
+    }
+    if p != pattern.len() {
+        return None;
+    }
+
+    // Prefer tighter matches and shorter names
+    let spread = matched[matched.len() - 1] - matched[0];
+    score -= spread as i64 + chars.len() as i64 / 10;
+    Some((score, matched))
+}
+
+/// Finds the index of the best scoring name, if any matched.
+pub fn best_match<'a, I>(pattern: &str, names: I) -> Option<usize>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, name)| fuzzy_match(pattern, name).map(|(score, _)| (i, score)))
+        .max_by_key(|&(i, score)| (score, std::cmp::Reverse(i)))
+        .map(|(i, _)| i)
+}
+
+/// The search across every list, opened with `/` from the list panel.
+#[derive(Default)]
+pub struct Finder {
+    pub query: String,
+    /// Index of the highlighted result
+    pub selected: usize,
+}
+
+impl Finder {
+    /// Tasks from every list whose name or one of whose tags matches the
+    /// query, best match first.
+    pub fn results(&self, lists: &[TaskList]) -> Vec<TaskRef> {
+        let mut found: Vec<(i64, TaskRef)> = lists
+            .iter()
+            .enumerate()
+            .flat_map(|(l, list)| {
+                list.tasks
+                    .iter()
+                    .enumerate()
+                    .map(move |(t, task)| ((l, t), task))
+            })
+            .filter_map(|(task_ref, task)| {
+                let name = fuzzy_match(&self.query, &task.name).map(|(score, _)| score);
+                // A match in a tag counts for a little less than one in the name
+                let tag = task
+                    .tags
+                    .iter()
+                    .filter_map(|tag| fuzzy_match(&self.query, tag).map(|(score, _)| score - 1))
+                    .max();
+                name.max(tag).map(|score| (score, task_ref))
+            })
+            .collect();
+        found.sort_by_key(|&(score, task_ref)| (std::cmp::Reverse(score), task_ref));
+        found.into_iter().map(|(_, task_ref)| task_ref).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ignore_case() {
+        let (_, matched) = fuzzy_match("BuY", "buy Milk").unwrap();
+        assert_eq!(matched, [0, 1, 2]);
+        let (_, matched) = fuzzy_match("milk", "Buy MILK").unwrap();
+        assert_eq!(matched, [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn characters_must_come_in_order() {
+        assert!(fuzzy_match("bm", "buy milk").is_some());
+        assert!(fuzzy_match("mb", "buy milk").is_none());
+        assert!(fuzzy_match("milkk", "buy milk").is_none());
+    }
+
+    #[test]
+    fn empty_pattern_matches_nothing() {
+        assert!(fuzzy_match("", "buy milk").is_none());
+        assert_eq!(best_match("", ["a", "b"]), None);
+    }
+
+    #[test]
+    fn tight_matches_at_word_starts_score_higher() {
+        let score = |text| fuzzy_match("bm", text).unwrap().0;
+        assert!(score("buy milk") > score("bottom"));
+        assert_eq!(best_match("mi", ["admin", "milk"]), Some(1));
+    }
+}
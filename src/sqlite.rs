@@ -0,0 +1,191 @@
+use rusqlite::{params, Connection, Transaction};
+use std::path::{Path, PathBuf};
+
+use crate::db::{Error, ListColor, Task, TaskList};
+use crate::store::Store;
+
+/// Lists, tasks and their tags get their own tables so the file can be
+/// queried directly. The rest of the task is kept as JSON next to the
+/// queryable columns so new fields don't need a schema change.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS lists (
+        id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        color TEXT,
+        pinned INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        list_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        due_date TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        list_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL
+    );
+";
+
+fn open(path: &Path) -> Result<Connection, Error> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    // Files from before lists had colors
+    if conn.prepare("SELECT color FROM lists").is_err() {
+        conn.execute("ALTER TABLE lists ADD COLUMN color TEXT", [])?;
+    }
+    if conn.prepare("SELECT pinned FROM lists").is_err() {
+        conn.execute(
+            "ALTER TABLE lists ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    Ok(conn)
+}
+
+fn parse_color(name: &str) -> Option<ListColor> {
+    ListColor::ALL
+        .iter()
+        .copied()
+        .find(|color| color.name() == name)
+}
+
+fn load_db(path: &Path) -> Result<Vec<TaskList>, Error> {
+    if !path.exists() {
+        return Err(Error::ReadDBError(std::io::ErrorKind::NotFound.into()));
+    }
+    let conn = open(path)?;
+
+    let mut lists = conn
+        .prepare("SELECT id, name, color, pinned FROM lists ORDER BY position")?
+        .query_map([], |row| {
+            let color: Option<String> = row.get(2)?;
+            Ok(TaskList {
+                color: color.as_deref().and_then(parse_color),
+                pinned: row.get(3)?,
+                ..TaskList::new(row.get::<_, i64>(0)? as usize, row.get(1)?)
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tasks =
+        conn.prepare("SELECT id, data FROM tasks WHERE list_id = ? ORDER BY position")?;
+    let mut tags =
+        conn.prepare("SELECT name FROM tags WHERE list_id = ? AND task_id = ? ORDER BY position")?;
+    for list in &mut lists {
+        let rows = tasks.query_map(params![list.id as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, data) = row?;
+            let mut data: serde_json::Value = serde_json::from_str(&data)?;
+            // Files from before the tags table keep them in the JSON
+            if data.get("tags").is_none() {
+                let names = tags
+                    .query_map(params![list.id as i64, id], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                data["tags"] = names.into();
+            }
+            list.tasks.push(serde_json::from_value::<Task>(data)?);
+        }
+    }
+    Ok(lists)
+}
+
+fn insert_tasks(tx: &Transaction, list: &TaskList) -> Result<(), Error> {
+    for (position, task) in list.tasks.iter().enumerate() {
+        let mut data = serde_json::to_value(task)?;
+        if let Some(fields) = data.as_object_mut() {
+            fields.remove("tags");
+        }
+        tx.execute(
+            "INSERT INTO tasks (list_id, position, id, name, due_date, data)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                list.id as i64,
+                position as i64,
+                task.id as i64,
+                task.name,
+                task.due_date.to_rfc3339(),
+                data.to_string(),
+            ],
+        )?;
+        for (position, tag) in task.tags.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO tags (list_id, task_id, position, name) VALUES (?, ?, ?, ?)",
+                params![list.id as i64, task.id as i64, position as i64, tag],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_db(path: &Path, lists: &[TaskList]) -> Result<(), Error> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM tags", [])?;
+    tx.execute("DELETE FROM tasks", [])?;
+    tx.execute("DELETE FROM lists", [])?;
+    for (position, list) in lists.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO lists (id, position, name, color, pinned) VALUES (?, ?, ?, ?, ?)",
+            params![
+                list.id as i64,
+                position as i64,
+                list.name,
+                list.color.map(ListColor::name),
+                list.pinned
+            ],
+        )?;
+        insert_tasks(&tx, list)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Replaces one list's name and tasks, leaving the other lists alone.
+fn write_list(path: &Path, list: &TaskList) -> Result<(), Error> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "UPDATE lists SET name = ?, color = ?, pinned = ? WHERE id = ?",
+        params![
+            list.name,
+            list.color.map(ListColor::name),
+            list.pinned,
+            list.id as i64
+        ],
+    )?;
+    tx.execute(
+        "DELETE FROM tags WHERE list_id = ?",
+        params![list.id as i64],
+    )?;
+    tx.execute(
+        "DELETE FROM tasks WHERE list_id = ?",
+        params![list.id as i64],
+    )?;
+    insert_tasks(&tx, list)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// A SQLite file, saved in a transaction so a failed save changes nothing.
+pub struct SqliteStore(pub PathBuf);
+
+impl Store for SqliteStore {
+    fn load(&self) -> Result<Vec<TaskList>, Error> {
+        load_db(&self.0)
+    }
+
+    fn save(&self, lists: &[TaskList]) -> Result<(), Error> {
+        write_db(&self.0, lists)
+    }
+
+    fn save_list(&self, _lists: &[TaskList], list: &TaskList) -> Result<(), Error> {
+        write_list(&self.0, list)
+    }
+}
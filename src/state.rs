@@ -0,0 +1,74 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command::SortKey;
+use crate::db::{write_atomic, Error, Task};
+
+const STATE_FILE: &str = "state.json";
+
+/// How a list's tasks are kept ordered.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+    /// Whether the list is sorted again after every change to it
+    #[serde(default)]
+    pub auto: bool,
+}
+
+impl SortOrder {
+    pub fn apply(self, tasks: &mut [Task]) {
+        match self.key {
+            SortKey::Name => tasks.sort_by_key(|task| task.name.to_lowercase()),
+            SortKey::Start => tasks.sort_by_key(|task| task.start_date),
+            SortKey::Due => tasks.sort_by_key(|task| task.due_date),
+            SortKey::Priority => tasks.sort_by_key(|task| Reverse(task.priority)),
+        }
+        if self.descending {
+            tasks.reverse();
+        }
+    }
+}
+
+/// UI preferences remembered between runs, kept apart from the tasks so
+/// every storage backend shares them.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct State {
+    /// Sort order picked for each list, by list id
+    pub sort: HashMap<usize, SortOrder>,
+    /// Sidebar entry and task row that were highlighted on quit
+    pub selected_entry: usize,
+    pub selected_row: Option<usize>,
+}
+
+/// Puts `file` beside the db at `db_path`, prefixed with the db's name
+/// like `db.state.json`, so each db keeps its own state and trash.
+pub fn beside_db(db_path: &Path, file: &str) -> PathBuf {
+    let stem = db_path.file_stem().unwrap_or_default().to_string_lossy();
+    db_path.with_file_name(format!("{}.{}", stem, file))
+}
+
+pub fn read_state(db_path: &Path) -> State {
+    let path = beside_db(db_path, STATE_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return State::default(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|err| {
+        warn!("couldn't parse {}, starting fresh: {}", path.display(), err);
+        State::default()
+    })
+}
+
+pub fn write_state(db_path: &Path, state: &State) -> Result<(), Error> {
+    write_atomic(
+        &beside_db(db_path, STATE_FILE),
+        &serde_json::to_string_pretty(state)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::{write_atomic, Error, TaskList, DB_FILE, SPLIT_DIR, SQLITE_FILE, YAML_FILE};
+
+/// Where lists are loaded from and saved to.
+pub trait Store {
+    fn load(&self) -> Result<Vec<TaskList>, Error>;
+
+    fn save(&self, lists: &[TaskList]) -> Result<(), Error>;
+
+    /// Saves only `list`, one of `lists`. Single file stores have to rewrite
+    /// everything anyway.
+    fn save_list(&self, lists: &[TaskList], _list: &TaskList) -> Result<(), Error> {
+        self.save(lists)
+    }
+}
+
+/// The file or directory name in the data directory of the backend called
+/// `name` in the config.
+pub fn backend_file(name: &str) -> Option<&'static str> {
+    match name {
+        "json" => Some(DB_FILE),
+        "yaml" => Some(YAML_FILE),
+        "split" => Some(SPLIT_DIR),
+        "sqlite" => Some(SQLITE_FILE),
+        _ => None,
+    }
+}
+
+/// Picks the store for `path` by its extension: SQLite for `.db`, `.sqlite`
+/// and `.sqlite3`, YAML for `.yaml` and `.yml`, a directory of lists for no
+/// extension, and JSON for everything else.
+pub fn open_store(path: &Path) -> Result<Box<dyn Store>, Error> {
+    let path = path.to_path_buf();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("db") | Some("sqlite") | Some("sqlite3") => {
+            #[cfg(feature = "sqlite")]
+            return Ok(Box::new(crate::sqlite::SqliteStore(path)));
+            #[cfg(not(feature = "sqlite"))]
+            return Err(Error::NoSqliteError);
+        }
+        Some("yaml") | Some("yml") => Ok(Box::new(YamlStore(path))),
+        None => Ok(Box::new(SplitStore(path))),
+        Some(_) => Ok(Box::new(JsonStore(path))),
+    }
+}
+
+/// Every list in one pretty printed JSON file.
+pub struct JsonStore(pub PathBuf);
+
+impl Store for JsonStore {
+    fn load(&self) -> Result<Vec<TaskList>, Error> {
+        let value: serde_json::Value = serde_json::from_str(&fs::read_to_string(&self.0)?)?;
+        let migrate = has_local_offsets(&value);
+        let lists: Vec<TaskList> = serde_json::from_value(value)?;
+        if migrate {
+            info!("migrating {} to UTC dates", self.0.display());
+            self.save(&lists)?;
+        }
+        Ok(lists)
+    }
+
+    fn save(&self, lists: &[TaskList]) -> Result<(), Error> {
+        write_atomic(&self.0, &serde_json::to_string_pretty(lists)?)?;
+        Ok(())
+    }
+}
+
+/// Files from before dates were stored in UTC carry local offsets. They parse
+/// fine, but get rewritten so the file is portable between timezones.
+fn has_local_offsets(value: &serde_json::Value) -> bool {
+    let lists = value.as_array().into_iter().flatten();
+    let tasks = lists.flat_map(|list| list["tasks"].as_array().into_iter().flatten());
+    tasks
+        .flat_map(|task| {
+            ["start_date", "due_date"]
+                .iter()
+                .map(move |key| &task[*key])
+        })
+        .filter_map(|date| date.as_str())
+        .any(|date| !date.ends_with('Z'))
+}
+
+/// Every list in one YAML file, for editing by hand.
+pub struct YamlStore(pub PathBuf);
+
+impl Store for YamlStore {
+    fn load(&self) -> Result<Vec<TaskList>, Error> {
+        Ok(serde_yaml::from_str(&fs::read_to_string(&self.0)?)?)
+    }
+
+    fn save(&self, lists: &[TaskList]) -> Result<(), Error> {
+        write_atomic(&self.0, &serde_yaml::to_string(lists)?)?;
+        Ok(())
+    }
+}
+
+/// Order of the lists in a split directory, by id
+const INDEX_FILE: &str = "index.json";
+
+/// A directory holding one JSON file per list, so saving one list leaves the
+/// others' files alone.
+pub struct SplitStore(pub PathBuf);
+
+impl SplitStore {
+    fn list_file(&self, id: usize) -> PathBuf {
+        self.0.join(format!("{}.json", id))
+    }
+}
+
+impl Store for SplitStore {
+    fn load(&self) -> Result<Vec<TaskList>, Error> {
+        let index: Vec<usize> =
+            serde_json::from_str(&fs::read_to_string(self.0.join(INDEX_FILE))?)?;
+        index
+            .into_iter()
+            .map(|id| {
+                Ok(serde_json::from_str(&fs::read_to_string(
+                    self.list_file(id),
+                )?)?)
+            })
+            .collect()
+    }
+
+    fn save(&self, lists: &[TaskList]) -> Result<(), Error> {
+        fs::create_dir_all(&self.0)?;
+        for list in lists {
+            self.save_list(lists, list)?;
+        }
+        let index: Vec<usize> = lists.iter().map(|list| list.id).collect();
+        write_atomic(
+            &self.0.join(INDEX_FILE),
+            &serde_json::to_string_pretty(&index)?,
+        )?;
+        // Files of deleted lists
+        for entry in fs::read_dir(&self.0)? {
+            let path = entry?.path();
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<usize>().ok());
+            if id.is_some_and(|id| !index.contains(&id)) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn save_list(&self, _lists: &[TaskList], list: &TaskList) -> Result<(), Error> {
+        write_atomic(
+            &self.list_file(list.id),
+            &serde_json::to_string_pretty(list)?,
+        )?;
+        Ok(())
+    }
+}
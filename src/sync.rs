@@ -0,0 +1,664 @@
+use chrono::prelude::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono::Duration;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::CaldavConfig;
+use crate::db::{write_atomic, Error, Priority, Status, Task, TaskList};
+use crate::export::{ics_calendar, vtodo_lines};
+use crate::state::beside_db;
+use crate::trash::Trashed;
+
+const SYNC_FILE: &str = "sync.json";
+
+/// A calendar on the server that can hold to-dos.
+pub struct Calendar {
+    pub href: String,
+    pub name: String,
+}
+
+/// A to-do as it is on the server.
+#[derive(Clone)]
+pub struct RemoteTodo {
+    pub href: String,
+    pub etag: String,
+    pub ics: String,
+}
+
+/// The server lists are synced with. That's CalDAV outside of the tests.
+pub trait Remote {
+    fn calendars(&mut self) -> Result<Vec<Calendar>, Error>;
+
+    fn create_calendar(&mut self, name: &str) -> Result<Calendar, Error>;
+
+    fn todos(&mut self, calendar: &Calendar) -> Result<Vec<RemoteTodo>, Error>;
+
+    /// Uploads a to-do if it still has `etag` on the server, or if it isn't
+    /// there at all for `None`. Returns its new etag.
+    fn put(&mut self, href: &str, ics: &str, etag: Option<&str>) -> Result<String, Error>;
+
+    fn delete(&mut self, href: &str, etag: &str) -> Result<(), Error>;
+}
+
+/// The CalDAV server set up in the config, if support for it was built in.
+pub fn open_remote(config: &CaldavConfig) -> Result<Box<dyn Remote>, Error> {
+    #[cfg(feature = "caldav")]
+    return Ok(Box::new(crate::caldav::Caldav::new(config)));
+    #[cfg(not(feature = "caldav"))]
+    {
+        let _ = config;
+        Err(Error::NoCaldavError)
+    }
+}
+
+/// A task that's been on the server, and the etag it had after the last sync.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Synced {
+    pub list: usize,
+    pub task: usize,
+    pub uid: String,
+    pub href: String,
+    pub etag: String,
+}
+
+/// What the last sync left behind, kept beside the db so the next one can
+/// tell which side changed.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SyncState {
+    /// When the last sync started, tasks edited after it are pushed
+    pub last_sync: Option<DateTime<Utc>>,
+    /// Calendar href for each list, by list id. Lists deleted here keep
+    /// theirs so the calendar isn't pulled back in.
+    pub calendars: HashMap<usize, String>,
+    pub tasks: Vec<Synced>,
+}
+
+pub fn read_sync_state(db_path: &Path) -> SyncState {
+    let path = beside_db(db_path, SYNC_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return SyncState::default(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|err| {
+        warn!(
+            "couldn't parse {}, syncing from scratch: {}",
+            path.display(),
+            err
+        );
+        SyncState::default()
+    })
+}
+
+pub fn write_sync_state(db_path: &Path, state: &SyncState) -> Result<(), Error> {
+    write_atomic(
+        &beside_db(db_path, SYNC_FILE),
+        &serde_json::to_string_pretty(state)?,
+    )?;
+    Ok(())
+}
+
+/// The fields of a VTODO that map onto a task.
+#[derive(Clone)]
+struct Todo {
+    uid: String,
+    name: String,
+    start: Option<DateTime<Utc>>,
+    due: Option<DateTime<Utc>>,
+    status: Status,
+    completed: Option<DateTime<Utc>>,
+    priority: Priority,
+    categories: Vec<String>,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl Todo {
+    /// Copies the to-do onto `task`, leaving what iCalendar has no room for,
+    /// like comments and tracked time, alone.
+    fn apply(&self, task: &mut Task) {
+        task.name = self.name.clone();
+        if let Some(start) = self.start {
+            task.start_date = start;
+        }
+        task.due_date = self
+            .due
+            .unwrap_or_else(|| task.due_date.max(task.start_date + Duration::days(1)));
+        task.status = match self.status {
+            // Waiting goes up as NEEDS-ACTION, so it shouldn't come back as Todo
+            Status::Todo if task.status == Status::Waiting => Status::Waiting,
+            status => status,
+        };
+        task.completed_at = match task.status {
+            Status::Done => self.completed.or(task.completed_at).or(Some(Utc::now())),
+            _ => None,
+        };
+        task.priority = self.priority;
+        task.tags = self.categories.clone();
+    }
+}
+
+/// Joins folded lines back up.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Splits a list value on the commas that aren't escaped.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            _ => items.last_mut().unwrap().push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    items
+        .iter()
+        .map(|item| unescape(item))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Reads a DATE or DATE-TIME value. Times with a TZID or none at all are
+/// taken as local, which is right for the servers' own clients.
+fn parse_ics_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&date));
+    }
+    let date = match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(day) => day.and_hms_opt(0, 0, 0)?,
+        Err(_) => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?,
+    };
+    Some(
+        Local
+            .from_local_datetime(&date)
+            .earliest()?
+            .with_timezone(&Utc),
+    )
+}
+
+/// iCalendar priorities run from 1 for the highest to 9 for the lowest, with
+/// 0 for none.
+fn priority(value: &str) -> Priority {
+    match value.parse::<u8>() {
+        Ok(1) => Priority::Urgent,
+        Ok(2..=4) => Priority::High,
+        Ok(6..=9) => Priority::Low,
+        _ => Priority::Medium,
+    }
+}
+
+/// Reads the first VTODO in a calendar object, skipping its alarms.
+fn parse_vtodo(ics: &str) -> Option<Todo> {
+    let mut todo = Todo {
+        uid: String::new(),
+        name: String::new(),
+        start: None,
+        due: None,
+        status: Status::Todo,
+        completed: None,
+        priority: Priority::Medium,
+        categories: Vec::new(),
+        modified: None,
+    };
+    let mut depth = 0;
+    let mut found = false;
+    for line in unfold(ics) {
+        let (name, value) = match line.split_once(':') {
+            Some(property) => property,
+            None => continue,
+        };
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+        match (name.as_str(), value) {
+            ("BEGIN", "VTODO") if !found => {
+                found = true;
+                depth = 1;
+                continue;
+            }
+            ("BEGIN", _) if depth > 0 => depth += 1,
+            ("END", _) if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        if depth != 1 {
+            continue;
+        }
+        match name.as_str() {
+            "UID" => todo.uid = value.to_string(),
+            "SUMMARY" => todo.name = unescape(value),
+            "DTSTART" => todo.start = parse_ics_date(value),
+            "DUE" => todo.due = parse_ics_date(value),
+            "COMPLETED" => todo.completed = parse_ics_date(value),
+            "LAST-MODIFIED" => todo.modified = parse_ics_date(value),
+            "PRIORITY" => todo.priority = priority(value),
+            "CATEGORIES" => todo.categories.extend(split_list(value)),
+            "STATUS" => {
+                todo.status = match value {
+                    "IN-PROCESS" => Status::InProgress,
+                    "COMPLETED" | "CANCELLED" => Status::Done,
+                    _ => Status::Todo,
+                }
+            }
+            _ => {}
+        }
+    }
+    if todo.completed.is_some() {
+        todo.status = Status::Done;
+    }
+    Some(todo).filter(|_| found)
+}
+
+/// A task changed on both sides since the last sync, waiting for the user to
+/// pick one.
+pub struct Conflict {
+    pub list: usize,
+    pub task: usize,
+    pub name: String,
+    /// Whether the server's copy was edited after the local one
+    pub remote_newer: bool,
+    remote: RemoteTodo,
+    todo: Todo,
+}
+
+/// What a sync did, for the flash message and the trash.
+#[derive(Default)]
+pub struct SyncReport {
+    pub pulled: usize,
+    pub pushed: usize,
+    /// Tasks deleted on the server, for the trash
+    pub removed: Vec<Trashed>,
+    pub conflicts: Vec<Conflict>,
+}
+
+fn new_uid() -> String {
+    format!("tbg-{:016x}", rand::random::<u64>())
+}
+
+fn task_ics(uid: &str, task: &Task) -> String {
+    ics_calendar(&[vtodo_lines(uid, task, &[])])
+}
+
+/// Makes every list a calendar on the server and every task a to-do in it,
+/// and brings in what the server has that isn't here yet. A task edited on
+/// only one side since the last sync takes that side's copy, one edited on
+/// both becomes a conflict for the user to settle with [`resolve`].
+pub fn sync(
+    lists: &mut Vec<TaskList>,
+    state: &mut SyncState,
+    remote: &mut dyn Remote,
+) -> Result<SyncReport, Error> {
+    let started = Utc::now();
+    let last_sync = state.last_sync;
+    let changed_here =
+        |task: &Task| last_sync.is_none_or(|at| task.updated_at.is_some_and(|edit| edit > at));
+    let mut report = SyncReport::default();
+
+    // Lists keep the calendar they had, or take the one named after them
+    let mut calendars = remote.calendars()?;
+    for list in lists.iter() {
+        let known = state
+            .calendars
+            .get(&list.id)
+            .filter(|href| calendars.iter().any(|calendar| calendar.href == **href))
+            .cloned();
+        let named = || {
+            calendars
+                .iter()
+                .find(|calendar| calendar.name.eq_ignore_ascii_case(&list.name))
+                .map(|calendar| calendar.href.clone())
+        };
+        let href = match known.or_else(named) {
+            Some(href) => href,
+            None => {
+                let calendar = remote.create_calendar(&list.name)?;
+                let href = calendar.href.clone();
+                calendars.push(calendar);
+                href
+            }
+        };
+        // Tasks are pushed again to a calendar that replaces one deleted on
+        // the server, rather than deleted here
+        if state.calendars.insert(list.id, href.clone()) != Some(href) {
+            state.tasks.retain(|entry| entry.list != list.id);
+        }
+    }
+    for calendar in &calendars {
+        if !state.calendars.values().any(|href| *href == calendar.href) {
+            let id = lists.iter().map(|l| l.id + 1).max().unwrap_or(0);
+            lists.push(TaskList::new(id, calendar.name.clone()));
+            state.calendars.insert(id, calendar.href.clone());
+        }
+    }
+
+    let mut synced = Vec::new();
+    let mappings: Vec<(usize, String)> = state
+        .calendars
+        .iter()
+        .map(|(&list, href)| (list, href.clone()))
+        .collect();
+    for (list_id, href) in mappings {
+        let calendar = match calendars.iter().find(|calendar| calendar.href == href) {
+            Some(calendar) => calendar,
+            None => continue,
+        };
+        let list = lists.iter().position(|list| list.id == list_id);
+        let mut todos: HashMap<String, RemoteTodo> = remote
+            .todos(calendar)?
+            .into_iter()
+            .map(|todo| (todo.href.clone(), todo))
+            .collect();
+
+        for entry in state.tasks.iter().filter(|entry| entry.list == list_id) {
+            let server = todos.remove(&entry.href);
+            let task = list.and_then(|list| {
+                let task = lists[list]
+                    .tasks
+                    .iter()
+                    .position(|task| task.id == entry.task)?;
+                Some((list, task))
+            });
+            let (list, task, server) = match (task, server) {
+                (Some(local), Some(server)) => (local.0, local.1, server),
+                (Some((list, position)), None) => {
+                    report.removed.push(Trashed {
+                        list_id,
+                        position,
+                        task: lists[list].tasks.remove(position),
+                    });
+                    continue;
+                }
+                (None, Some(server)) => {
+                    remote.delete(&server.href, &server.etag)?;
+                    continue;
+                }
+                (None, None) => continue,
+            };
+            let task = &mut lists[list].tasks[task];
+            let mut entry = entry.clone();
+            match (changed_here(task), server.etag != entry.etag) {
+                (false, false) => {}
+                (true, false) => {
+                    let ics = task_ics(&entry.uid, task);
+                    entry.etag = remote.put(&entry.href, &ics, Some(&entry.etag))?;
+                    report.pushed += 1;
+                }
+                (false, true) => {
+                    if let Some(todo) = parse_vtodo(&server.ics) {
+                        todo.apply(task);
+                        task.updated_at = Some(todo.modified.unwrap_or(started).min(started));
+                        report.pulled += 1;
+                    }
+                    entry.etag = server.etag;
+                }
+                (true, true) => match parse_vtodo(&server.ics) {
+                    Some(todo) => report.conflicts.push(Conflict {
+                        list: list_id,
+                        task: task.id,
+                        name: task.name.clone(),
+                        remote_newer: todo.modified > task.updated_at,
+                        remote: server,
+                        todo,
+                    }),
+                    // Nothing worth keeping on the server
+                    None => {
+                        let ics = task_ics(&entry.uid, task);
+                        entry.etag = remote.put(&entry.href, &ics, Some(&server.etag))?;
+                        report.pushed += 1;
+                    }
+                },
+            }
+            synced.push(entry);
+        }
+
+        let list = match list {
+            Some(list) => list,
+            None => continue,
+        };
+        // New on the server
+        let mut todos: Vec<RemoteTodo> = todos.into_values().collect();
+        todos.sort_by(|a, b| a.href.cmp(&b.href));
+        for server in todos {
+            let todo = match parse_vtodo(&server.ics) {
+                Some(todo) => todo,
+                None => continue,
+            };
+            let tasks = &mut lists[list].tasks;
+            let id = tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+            let mut task = Task::new(id, String::new(), started);
+            todo.apply(&mut task);
+            task.updated_at = Some(todo.modified.unwrap_or(started).min(started));
+            tasks.push(task);
+            synced.push(Synced {
+                list: list_id,
+                task: id,
+                uid: todo.uid,
+                href: server.href,
+                etag: server.etag,
+            });
+            report.pulled += 1;
+        }
+        // New here
+        for task in &lists[list].tasks {
+            let known = synced
+                .iter()
+                .any(|entry| entry.list == list_id && entry.task == task.id);
+            if known {
+                continue;
+            }
+            let uid = new_uid();
+            let href = format!("{}/{}.ics", href.trim_end_matches('/'), uid);
+            let etag = remote.put(&href, &task_ics(&uid, task), None)?;
+            synced.push(Synced {
+                list: list_id,
+                task: task.id,
+                uid,
+                href,
+                etag,
+            });
+            report.pushed += 1;
+        }
+    }
+    state.tasks = synced;
+    state.last_sync = Some(started);
+    Ok(report)
+}
+
+/// Settles a conflict by keeping the local task and overwriting the server's
+/// copy, or the other way round.
+pub fn resolve(
+    lists: &mut [TaskList],
+    state: &mut SyncState,
+    remote: &mut dyn Remote,
+    conflict: Conflict,
+    keep_local: bool,
+) -> Result<(), Error> {
+    let task = lists
+        .iter_mut()
+        .find(|list| list.id == conflict.list)
+        .and_then(|list| list.tasks.iter_mut().find(|task| task.id == conflict.task));
+    let entry = state
+        .tasks
+        .iter_mut()
+        .find(|entry| entry.list == conflict.list && entry.task == conflict.task);
+    let (task, entry) = match (task, entry) {
+        (Some(task), Some(entry)) => (task, entry),
+        _ => return Ok(()),
+    };
+    if keep_local {
+        let ics = task_ics(&entry.uid, task);
+        entry.etag = remote.put(&entry.href, &ics, Some(&conflict.remote.etag))?;
+    } else {
+        conflict.todo.apply(task);
+        // Not an edit of ours, so the next sync doesn't push it back
+        task.updated_at = state.last_sync;
+        entry.etag = conflict.remote.etag;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "caldav"))]
+mod tests {
+    use super::*;
+    use crate::app::Mode;
+    use crate::input::tests::{app, list, names, press};
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    /// A CalDAV server in memory, shared so the test can change it between
+    /// syncs. To-dos are kept by href with their etag and text.
+    #[derive(Clone, Default)]
+    struct FakeServer(Rc<RefCell<FakeData>>);
+
+    #[derive(Default)]
+    struct FakeData {
+        calendars: Vec<String>,
+        todos: BTreeMap<String, (String, String)>,
+    }
+
+    impl FakeServer {
+        fn put_ics(&self, href: &str, ics: &str) {
+            let etag = format!("\"{}\"", rand::random::<u32>());
+            let todos = &mut self.0.borrow_mut().todos;
+            todos.insert(href.to_string(), (etag, ics.to_string()));
+        }
+    }
+
+    impl Remote for FakeServer {
+        fn calendars(&mut self) -> Result<Vec<Calendar>, Error> {
+            let names = &self.0.borrow().calendars;
+            Ok(names
+                .iter()
+                .map(|name| Calendar {
+                    href: format!("/{}/", name.to_lowercase()),
+                    name: name.clone(),
+                })
+                .collect())
+        }
+
+        fn create_calendar(&mut self, name: &str) -> Result<Calendar, Error> {
+            self.0.borrow_mut().calendars.push(name.to_string());
+            Ok(self.calendars()?.pop().unwrap())
+        }
+
+        fn todos(&mut self, calendar: &Calendar) -> Result<Vec<RemoteTodo>, Error> {
+            let todos = &self.0.borrow().todos;
+            Ok(todos
+                .iter()
+                .filter(|(href, _)| href.starts_with(&calendar.href))
+                .map(|(href, (etag, ics))| RemoteTodo {
+                    href: href.clone(),
+                    etag: etag.clone(),
+                    ics: ics.clone(),
+                })
+                .collect())
+        }
+
+        fn put(&mut self, href: &str, ics: &str, etag: Option<&str>) -> Result<String, Error> {
+            let current = self
+                .0
+                .borrow()
+                .todos
+                .get(href)
+                .map(|(etag, _)| etag.clone());
+            assert_eq!(current.as_deref(), etag, "put {} with a stale etag", href);
+            self.put_ics(href, ics);
+            Ok(self.0.borrow().todos[href].0.clone())
+        }
+
+        fn delete(&mut self, href: &str, _etag: &str) -> Result<(), Error> {
+            self.0.borrow_mut().todos.remove(href);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sync_merges_both_sides_and_asks_about_conflicts() {
+        let server = FakeServer::default();
+        server.0.borrow_mut().calendars.push("Work".to_string());
+        server.put_ics(
+            "/work/call.ics",
+            "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:call\r\nSUMMARY:Call Bob\\, then \r\n Alice\r\n\
+             DUE;TZID=Europe/Paris:20300102T090000\r\nPRIORITY:1\r\nCATEGORIES:phone,calls\r\n\
+             BEGIN:VALARM\r\nSUMMARY:Alarm\r\nEND:VALARM\r\nEND:VTODO\r\nEND:VCALENDAR\r\n",
+        );
+        let mut app = app(vec![list(0, "Home", &["a", "b"])]);
+        std::fs::create_dir_all(app.db_path.parent().unwrap()).unwrap();
+        app.sync_with(Box::new(server.clone())).unwrap();
+        assert_eq!(app.tasklists[1].name, "Work");
+        let call = &app.tasklists[1].tasks[0];
+        assert_eq!(call.name, "Call Bob, then Alice");
+        assert_eq!(call.priority, Priority::Urgent);
+        assert_eq!(call.tags, ["phone", "calls"]);
+        assert_eq!(server.0.borrow().calendars, ["Work", "Home"]);
+        assert_eq!(server.0.borrow().todos.len(), 3);
+
+        // Edited on both sides, deleted on the server, and edited only here
+        let href_of = |name: &str| {
+            let todos = &server.0.borrow().todos;
+            let summary = format!("SUMMARY:{}\r\n", name);
+            let (href, _) = todos
+                .iter()
+                .find(|(_, (_, ics))| ics.contains(&summary))
+                .unwrap();
+            href.clone()
+        };
+        let (a, b) = (href_of("a"), href_of("b"));
+        let phone = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:a\r\nSUMMARY:a from the phone\r\n\
+                     LAST-MODIFIED:20990101T000000Z\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        server.put_ics(&a, phone);
+        server.0.borrow_mut().todos.remove(&b);
+        let later = Some(Utc::now() + Duration::seconds(1));
+        app.tasklists[0].tasks[0].name = "a from here".to_string();
+        app.tasklists[0].tasks[0].updated_at = later;
+        app.tasklists[1].tasks[0].name = "Call Bob".to_string();
+        app.tasklists[1].tasks[0].updated_at = later;
+        app.sync_with(Box::new(server.clone())).unwrap();
+        assert_eq!(names(&app, 0), ["a from here"]);
+        assert_eq!(app.trash.last().unwrap().task.name, "b");
+        assert!(server.0.borrow().todos["/work/call.ics"]
+            .1
+            .contains("SUMMARY:Call Bob\r\n"));
+        assert_eq!(app.mode(), Mode::Confirm);
+        // Only y or n settle it, y keeping the newer copy from the server
+        press(&mut app, "x");
+        assert_eq!(app.mode(), Mode::Confirm);
+        press(&mut app, "y");
+        assert_eq!(app.mode(), Mode::Normal);
+        assert_eq!(names(&app, 0), ["a from the phone"]);
+        assert_eq!(server.0.borrow().todos[&a].1, phone);
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::prelude::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::db::{Comment, Error, Priority, Status, Task, TaskList};
+use crate::export::write_export;
+use crate::import::Imported;
+
+/// Dates in Taskwarrior's JSON are compact UTC, like 20240301T120000Z.
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// One task as `task export` writes it and `task import` reads it. Fields
+/// tbg has no use for, like urgency, are left out.
+#[derive(Serialize, Deserialize)]
+struct TwTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    description: String,
+    status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    /// When the task was started, only set while it's active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    /// When the task was completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Annotation {
+    entry: String,
+    description: String,
+}
+
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(date, DATE_FORMAT)
+        .ok()
+        .map(|date| Utc.from_utc_datetime(&date))
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// A Taskwarrior task as a tbg one, or `None` for deleted tasks and the
+/// templates recurring tasks are made from.
+fn from_taskwarrior(tw: TwTask) -> Option<Imported> {
+    let date = |date: &Option<String>| date.as_deref().and_then(parse_date);
+    let mut task = Task::new(0, tw.description, Utc::now());
+    task.status = match tw.status.as_str() {
+        "pending" if tw.start.is_some() => Status::InProgress,
+        "pending" => Status::Todo,
+        "waiting" => Status::Waiting,
+        "completed" => Status::Done,
+        _ => return None,
+    };
+    if task.status == Status::Done {
+        task.completed_at = Some(date(&tw.end).unwrap_or_else(Utc::now));
+    }
+    task.tags = tw.tags;
+    task.priority = match tw.priority.as_deref() {
+        Some("H") => Priority::High,
+        Some("L") => Priority::Low,
+        _ => Priority::Medium,
+    };
+    task.start_date = date(&tw.entry).unwrap_or(task.start_date);
+    // Tasks without a due date are given a day
+    task.due_date =
+        date(&tw.due).unwrap_or_else(|| task.start_date.max(Utc::now()) + Duration::days(1));
+    task.updated_at = date(&tw.modified);
+    task.comments = tw
+        .annotations
+        .into_iter()
+        .filter_map(|annotation| {
+            Some(Comment {
+                at: parse_date(&annotation.entry)?,
+                text: annotation.description,
+            })
+        })
+        .collect();
+    Some(Imported {
+        project: tw.project,
+        task,
+    })
+}
+
+/// Reads the JSON array `task export` writes.
+pub fn parse_taskwarrior(content: &str) -> Result<Vec<Imported>, Error> {
+    let tasks: Vec<TwTask> = serde_json::from_str(content)?;
+    Ok(tasks.into_iter().filter_map(from_taskwarrior).collect())
+}
+
+/// A tbg task as a Taskwarrior one in the project named after its list. The
+/// uuid comes from the list and task ids, so importing an export again
+/// updates the tasks instead of adding them twice.
+fn to_taskwarrior(list: &TaskList, task: &Task) -> TwTask {
+    let status = match task.status {
+        Status::Done => "completed",
+        Status::Todo | Status::InProgress | Status::Waiting => "pending",
+    };
+    let priority = match task.priority {
+        Priority::Urgent | Priority::High => Some("H"),
+        Priority::Medium => None,
+        Priority::Low => Some("L"),
+    };
+    let start = match task.status {
+        Status::InProgress => Some(task.running_since.unwrap_or(task.last_edited())),
+        _ => None,
+    };
+    TwTask {
+        uuid: Some(format!("{:08x}-0000-4000-8000-{:012x}", list.id, task.id)),
+        description: task.name.clone(),
+        status: status.to_string(),
+        project: Some(list.name.clone()),
+        tags: task.tags.clone(),
+        priority: priority.map(str::to_string),
+        entry: Some(format_date(task.start_date)),
+        due: Some(format_date(task.due_date)),
+        start: start.map(format_date),
+        end: task.completed_at.map(format_date),
+        modified: task.updated_at.map(format_date),
+        annotations: task
+            .comments
+            .iter()
+            .map(|comment| Annotation {
+                entry: format_date(comment.at),
+                description: comment.text.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Writes every task as JSON `task import` reads, to stdout for `-`.
+pub fn export_taskwarrior(lists: &[TaskList], path: &Path) -> Result<(), Error> {
+    let tasks: Vec<TwTask> = lists
+        .iter()
+        .flat_map(|list| {
+            list.tasks
+                .iter()
+                .map(move |task| to_taskwarrior(list, task))
+        })
+        .collect();
+    write_export(path, &serde_json::to_string_pretty(&tasks)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::scratch_db;
+    use crate::import::{import_file, ImportFormat};
+    use std::fs;
+
+    fn list(id: usize, name: &str, tasks: &[&str]) -> TaskList {
+        TaskList {
+            tasks: tasks
+                .iter()
+                .enumerate()
+                .map(|(i, task)| Task::new(i, task.to_string(), Utc::now() + Duration::days(30)))
+                .collect(),
+            ..TaskList::new(id, name.to_string())
+        }
+    }
+
+    #[test]
+    fn taskwarrior_export_imports_back_into_lists() {
+        let mut exported = vec![list(0, "Home", &["a", "b"]), list(1, "Work", &["c"])];
+        let a = &mut exported[0].tasks[0];
+        a.tags = vec!["chores".to_string()];
+        a.priority = Priority::High;
+        a.status = Status::Done;
+        a.completed_at = Some(Utc::now());
+        let path = scratch_db().with_file_name("tasks.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        export_taskwarrior(&exported, &path).unwrap();
+
+        let mut lists = vec![list(0, "work", &["d"])];
+        let counts = import_file(&mut lists, ImportFormat::Taskwarrior, &path).unwrap();
+        assert_eq!(counts, (3, 1));
+        let names: Vec<&str> = lists.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["work", "Home"]);
+        let a = &lists[1].tasks[0];
+        assert_eq!(a.name, "a");
+        assert_eq!(a.tags, ["chores"]);
+        assert_eq!(a.priority, Priority::High);
+        assert!(a.is_completed());
+        let due = exported[0].tasks[0].due_date.timestamp();
+        assert_eq!(a.due_date.timestamp(), due);
+        assert_eq!(lists[0].tasks[1].name, "c");
+    }
+}
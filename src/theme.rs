@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use tui::style::Color;
+
+/// The colors everything is drawn in, picked by role rather than by hue so a
+/// preset can swap them all at once.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    /// Borders and plain text
+    pub text: Color,
+    /// The "Tasks But Good" banner
+    pub title: Color,
+    /// Text of the selected list, task or day
+    pub selected_fg: Color,
+    /// Background of the selected list, task or day
+    pub selected_bg: Color,
+    /// The focused field or item in popups, search matches and questions
+    pub active: Color,
+    /// Secondary details like list names in the buckets, and finished tasks
+    pub muted: Color,
+    /// Marks, key names, day headers and the bucket entries
+    pub marker: Color,
+    /// Errors, overdue due dates and urgent tasks
+    pub error: Color,
+    /// Overdue counts in the sidebar, which have to stand out on the
+    /// selected list's background too
+    pub badge: Color,
+    pub due_soon: Color,
+    pub due_near: Color,
+    pub due_later: Color,
+    /// Running timers, finished subtasks and the Done status
+    pub done: Color,
+    /// The agenda entry in the sidebar
+    pub agenda_title: Color,
+    /// The Waiting status
+    pub waiting: Color,
+    /// Medium priority, the default, so usually left plain
+    pub priority_medium: Color,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        text: Color::White,
+        title: Color::Red,
+        selected_fg: Color::Black,
+        selected_bg: Color::Red,
+        active: Color::Yellow,
+        muted: Color::DarkGray,
+        marker: Color::Cyan,
+        error: Color::Red,
+        badge: Color::LightRed,
+        due_soon: Color::Rgb(255, 165, 0),
+        due_near: Color::Yellow,
+        due_later: Color::Green,
+        done: Color::Green,
+        agenda_title: Color::Magenta,
+        waiting: Color::Magenta,
+        priority_medium: Color::Reset,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        text: Color::Black,
+        title: Color::Blue,
+        selected_fg: Color::White,
+        selected_bg: Color::Blue,
+        active: Color::Magenta,
+        muted: Color::Gray,
+        marker: Color::Blue,
+        error: Color::Red,
+        badge: Color::LightRed,
+        due_soon: Color::Rgb(215, 95, 0),
+        due_near: Color::Rgb(175, 135, 0),
+        due_later: Color::Green,
+        done: Color::Green,
+        agenda_title: Color::Magenta,
+        waiting: Color::Magenta,
+        priority_medium: Color::Reset,
+    };
+
+    /// Ethan Schoonover's Solarized, for a dark Solarized terminal
+    pub const SOLARIZED: Theme = Theme {
+        text: Color::Rgb(131, 148, 150),
+        title: Color::Rgb(38, 139, 210),
+        selected_fg: Color::Rgb(0, 43, 54),
+        selected_bg: Color::Rgb(38, 139, 210),
+        active: Color::Rgb(181, 137, 0),
+        muted: Color::Rgb(88, 110, 117),
+        marker: Color::Rgb(42, 161, 152),
+        error: Color::Rgb(220, 50, 47),
+        badge: Color::Rgb(211, 54, 130),
+        due_soon: Color::Rgb(203, 75, 22),
+        due_near: Color::Rgb(181, 137, 0),
+        due_later: Color::Rgb(133, 153, 0),
+        done: Color::Rgb(133, 153, 0),
+        agenda_title: Color::Rgb(108, 113, 196),
+        waiting: Color::Rgb(211, 54, 130),
+        priority_medium: Color::Rgb(131, 148, 150),
+    };
+
+    /// The preset called `name` with `overrides` (role to color) applied on
+    /// top. Bad entries are skipped and described in the returned errors.
+    pub fn new(name: &str, overrides: &HashMap<String, String>) -> (Theme, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut theme = match name {
+            "dark" => Theme::DARK,
+            "light" => Theme::LIGHT,
+            "solarized" => Theme::SOLARIZED,
+            _ => {
+                errors.push(format!("Unknown theme: {}", name));
+                Theme::DARK
+            }
+        };
+        for (role, color) in overrides {
+            let color = match parse_color(color) {
+                Some(color) => color,
+                None => {
+                    errors.push(format!("Unknown color for {}: {}", role, color));
+                    continue;
+                }
+            };
+            match theme.role(&role.replace('_', "-")) {
+                Some(slot) => *slot = color,
+                None => errors.push(format!("Unknown color role: {}", role)),
+            }
+        }
+        (theme, errors)
+    }
+
+    fn role(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "text" => &mut self.text,
+            "title" => &mut self.title,
+            "selected-fg" => &mut self.selected_fg,
+            "selected-bg" => &mut self.selected_bg,
+            "active" => &mut self.active,
+            "muted" => &mut self.muted,
+            "marker" => &mut self.marker,
+            "error" => &mut self.error,
+            "badge" => &mut self.badge,
+            "due-soon" => &mut self.due_soon,
+            "due-near" => &mut self.due_near,
+            "due-later" => &mut self.due_later,
+            "done" => &mut self.done,
+            "agenda-title" => &mut self.agenda_title,
+            "waiting" => &mut self.waiting,
+            "priority-medium" => &mut self.priority_medium,
+            _ => return None,
+        })
+    }
+}
+
+/// Reads a color name like "red" or "dark-gray", or a hex code like
+/// "#ff8800".
+fn parse_color(color: &str) -> Option<Color> {
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    Some(
+        match color.to_lowercase().replace(['-', '_'], "").as_str() {
+            "reset" | "default" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" | "grey" => Color::Gray,
+            "darkgray" | "darkgrey" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return None,
+        },
+    )
+}
@@ -0,0 +1,601 @@
+use chrono::prelude::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::config::TodoistConfig;
+use crate::db::{write_atomic, Error, Priority, Status, Task, TaskList};
+use crate::form::parse_date;
+use crate::state::beside_db;
+
+const TODOIST_FILE: &str = "todoist.json";
+
+/// A Todoist project, which becomes a list.
+#[derive(Deserialize, Clone)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Due {
+    /// The day, for tasks due some time that day
+    pub date: String,
+    /// The time, in UTC or floating for a time in every timezone
+    #[serde(default)]
+    pub datetime: Option<String>,
+}
+
+/// An open Todoist task.
+#[derive(Deserialize, Clone)]
+pub struct RemoteTask {
+    pub id: String,
+    pub project_id: String,
+    pub content: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// 1 for none up to 4 for the most urgent
+    pub priority: u8,
+    pub due: Option<Due>,
+}
+
+/// A local task as it's sent to Todoist.
+#[derive(Serialize, Clone)]
+pub struct NewTask {
+    pub content: String,
+    pub labels: Vec<String>,
+    pub priority: u8,
+    pub due_datetime: String,
+    /// Filled in once the list's project is known, left out of updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+/// A task that's in Todoist under `id`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Linked {
+    pub list: usize,
+    pub task: usize,
+    pub id: String,
+    /// Whether it's been closed in Todoist
+    #[serde(default)]
+    pub closed: bool,
+}
+
+/// What the last Todoist sync left behind, kept beside the db.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TodoistState {
+    /// When the last sync started, tasks edited after it are pushed
+    pub last_sync: Option<DateTime<Utc>>,
+    /// Project id for each list, by list id. Lists deleted here keep theirs
+    /// so the project isn't pulled back in.
+    pub projects: HashMap<usize, String>,
+    pub tasks: Vec<Linked>,
+}
+
+pub fn read_todoist_state(db_path: &Path) -> TodoistState {
+    let path = beside_db(db_path, TODOIST_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return TodoistState::default(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|err| {
+        warn!(
+            "couldn't parse {}, syncing from scratch: {}",
+            path.display(),
+            err
+        );
+        TodoistState::default()
+    })
+}
+
+pub fn write_todoist_state(db_path: &Path, state: &TodoistState) -> Result<(), Error> {
+    write_atomic(
+        &beside_db(db_path, TODOIST_FILE),
+        &serde_json::to_string_pretty(state)?,
+    )?;
+    Ok(())
+}
+
+/// Todoist has no low priority, so Low goes up as none like Medium.
+fn todoist_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Urgent => 4,
+        Priority::High => 3,
+        Priority::Medium | Priority::Low => 1,
+    }
+}
+
+fn priority(todoist: u8) -> Priority {
+    match todoist {
+        4 => Priority::Urgent,
+        3 => Priority::High,
+        _ => Priority::Medium,
+    }
+}
+
+/// A due time, or the start of a due day, both local.
+fn due_date(due: &Due) -> Option<DateTime<Utc>> {
+    match &due.datetime {
+        Some(time) => DateTime::parse_from_rfc3339(time)
+            .map(|time| time.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                let floating = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").ok()?;
+                Some(
+                    Local
+                        .from_local_datetime(&floating)
+                        .earliest()?
+                        .with_timezone(&Utc),
+                )
+            }),
+        None => parse_date(&due.date, "%Y-%m-%d", Utc::now()),
+    }
+}
+
+impl RemoteTask {
+    /// Copies what differs in Todoist's terms onto `task`, so a priority or
+    /// due time Todoist can't hold isn't lost on every pull.
+    fn apply(&self, task: &mut Task) {
+        task.name = self.content.clone();
+        task.tags = self.labels.clone();
+        if todoist_priority(task.priority) != self.priority {
+            task.priority = priority(self.priority);
+        }
+        let due = match &self.due {
+            Some(due) => due,
+            None => return,
+        };
+        let same_day = task
+            .due_date
+            .with_timezone(&Local)
+            .format("%Y-%m-%d")
+            .to_string()
+            == due.date;
+        if due.datetime.is_some() || !same_day {
+            if let Some(date) = due_date(due) {
+                task.due_date = date;
+            }
+        }
+    }
+}
+
+/// The changes to send to Todoist, worked out on the UI thread so the
+/// background one doesn't need the lists.
+#[cfg_attr(not(feature = "todoist"), allow(dead_code))]
+pub struct Plan {
+    /// Every list by id and name, for finding or making its project
+    pub lists: Vec<(usize, String)>,
+    pub projects: HashMap<usize, String>,
+    /// Open tasks that aren't in Todoist yet, by list id and task id
+    pub create: Vec<(usize, usize, NewTask)>,
+    /// Tasks edited here since the last sync, by Todoist id
+    pub update: Vec<(String, NewTask)>,
+    /// Tasks finished here
+    pub close: Vec<String>,
+    /// Tasks deleted here
+    pub delete: Vec<String>,
+}
+
+fn new_task(task: &Task) -> NewTask {
+    NewTask {
+        content: task.name.clone(),
+        labels: task.tags.clone(),
+        priority: todoist_priority(task.priority),
+        due_datetime: task.due_date.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        project_id: None,
+    }
+}
+
+pub fn plan(lists: &[TaskList], state: &TodoistState) -> Plan {
+    let edited = |task: &Task| {
+        state
+            .last_sync
+            .is_none_or(|at| task.updated_at.is_some_and(|edit| edit > at))
+    };
+    let mut plan = Plan {
+        lists: lists
+            .iter()
+            .map(|list| (list.id, list.name.clone()))
+            .collect(),
+        projects: state.projects.clone(),
+        create: Vec::new(),
+        update: Vec::new(),
+        close: Vec::new(),
+        delete: Vec::new(),
+    };
+    for list in lists {
+        for task in &list.tasks {
+            let linked = state
+                .tasks
+                .iter()
+                .find(|linked| linked.list == list.id && linked.task == task.id);
+            match linked {
+                Some(linked) if task.is_completed() && !linked.closed => {
+                    plan.close.push(linked.id.clone())
+                }
+                Some(linked) if !task.is_completed() && edited(task) => {
+                    plan.update.push((linked.id.clone(), new_task(task)))
+                }
+                Some(_) => {}
+                None if task.is_completed() => {}
+                None => plan.create.push((list.id, task.id, new_task(task))),
+            }
+        }
+    }
+    for linked in &state.tasks {
+        let exists = lists
+            .iter()
+            .find(|list| list.id == linked.list)
+            .is_some_and(|list| list.tasks.iter().any(|task| task.id == linked.task));
+        // Finished tasks cleared away here can stay finished in Todoist
+        if !exists && !linked.closed {
+            plan.delete.push(linked.id.clone());
+        }
+    }
+    plan
+}
+
+/// What Todoist said back: ids for what was made, and everything open.
+#[derive(Default)]
+pub struct Outcome {
+    /// Project id for each list, by list id
+    pub projects: HashMap<usize, String>,
+    /// New ids of the created tasks, by list id and task id
+    pub created: Vec<(usize, usize, String)>,
+    pub updated: usize,
+    pub closed: Vec<String>,
+    pub deleted: Vec<String>,
+    pub remote_projects: Vec<Project>,
+    pub remote_tasks: Vec<RemoteTask>,
+}
+
+/// Brings what Todoist has into `lists`: new projects as lists, new tasks,
+/// edits to tasks not edited here since `started`, and tasks no longer open
+/// as done. Returns how many tasks were pulled and pushed.
+pub fn merge(
+    lists: &mut Vec<TaskList>,
+    state: &mut TodoistState,
+    outcome: Outcome,
+    started: DateTime<Utc>,
+) -> (usize, usize) {
+    let pushed =
+        outcome.created.len() + outcome.updated + outcome.closed.len() + outcome.deleted.len();
+    state.projects.extend(outcome.projects);
+    for (list, task, id) in outcome.created {
+        state.tasks.push(Linked {
+            list,
+            task,
+            id,
+            closed: false,
+        });
+    }
+    let closed: HashSet<&String> = outcome.closed.iter().collect();
+    let deleted: HashSet<&String> = outcome.deleted.iter().collect();
+    state.tasks.retain(|linked| !deleted.contains(&linked.id));
+    for linked in &mut state.tasks {
+        linked.closed |= closed.contains(&linked.id);
+    }
+    for project in &outcome.remote_projects {
+        if !state.projects.values().any(|id| *id == project.id) {
+            let id = lists.iter().map(|l| l.id + 1).max().unwrap_or(0);
+            lists.push(TaskList::new(id, project.name.clone()));
+            state.projects.insert(id, project.id.clone());
+        }
+    }
+
+    let mut pulled = 0;
+    let open: HashSet<&String> = outcome.remote_tasks.iter().map(|task| &task.id).collect();
+    for remote in &outcome.remote_tasks {
+        let list_id = state
+            .projects
+            .iter()
+            .find(|(_, project)| **project == remote.project_id)
+            .map(|(&list, _)| list);
+        let list = match list_id.and_then(|id| lists.iter_mut().find(|list| list.id == id)) {
+            Some(list) => list,
+            None => continue,
+        };
+        match state.tasks.iter().find(|linked| linked.id == remote.id) {
+            Some(linked) => {
+                let task = list.tasks.iter_mut().find(|task| task.id == linked.task);
+                if let Some(task) =
+                    task.filter(|task| task.updated_at.is_none_or(|at| at <= started))
+                {
+                    remote.apply(task);
+                }
+            }
+            None => {
+                let id = list.tasks.iter().map(|t| t.id + 1).max().unwrap_or(0);
+                let mut task = Task::new(id, String::new(), Utc::now());
+                remote.apply(&mut task);
+                if remote.due.is_none() {
+                    task.due_date = task.start_date + chrono::Duration::days(1);
+                }
+                list.tasks.push(task);
+                state.tasks.push(Linked {
+                    list: list.id,
+                    task: id,
+                    id: remote.id.clone(),
+                    closed: false,
+                });
+                pulled += 1;
+            }
+        }
+    }
+    // Finished or deleted in Todoist, which look the same from here
+    for linked in state.tasks.iter_mut().filter(|linked| !linked.closed) {
+        if open.contains(&linked.id) {
+            continue;
+        }
+        linked.closed = true;
+        let task = lists
+            .iter_mut()
+            .find(|list| list.id == linked.list)
+            .and_then(|list| list.tasks.iter_mut().find(|task| task.id == linked.task));
+        if let Some(task) = task.filter(|task| !task.is_completed()) {
+            task.status = Status::Done;
+            task.completed_at = Some(Utc::now());
+            pulled += 1;
+        }
+    }
+    state.tasks.retain(|linked| {
+        !linked.closed
+            || lists
+                .iter()
+                .find(|list| list.id == linked.list)
+                .is_some_and(|list| list.tasks.iter().any(|task| task.id == linked.task))
+    });
+    state.last_sync = Some(started);
+    (pulled, pushed)
+}
+
+/// Runs `sync` on a thread of its own, so the UI keeps going while it
+/// talks to Todoist.
+pub fn spawn_sync<F>(sync: F, plan: Plan) -> Receiver<Result<Outcome, Error>>
+where
+    F: FnOnce(Plan) -> Result<Outcome, Error> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // The app may have quit in the meantime
+        let _ = tx.send(sync(plan));
+    });
+    rx
+}
+
+/// Sends `plan` to the Todoist REST API and reads back every open project
+/// and task.
+pub fn push_and_pull(config: &TodoistConfig, plan: Plan) -> Result<Outcome, Error> {
+    #[cfg(feature = "todoist")]
+    return api::push_and_pull(config, plan);
+    #[cfg(not(feature = "todoist"))]
+    {
+        let _ = (config, plan);
+        Err(Error::NoTodoistError)
+    }
+}
+
+#[cfg(feature = "todoist")]
+mod api {
+    use serde::de::DeserializeOwned;
+
+    use super::{Outcome, Plan, Project, RemoteTask};
+    use crate::config::TodoistConfig;
+    use crate::db::Error;
+
+    const API_URL: &str = "https://api.todoist.com/rest/v2";
+
+    fn sync_error(err: impl ToString) -> Error {
+        Error::SyncError(err.to_string())
+    }
+
+    struct Client<'a> {
+        agent: ureq::Agent,
+        token: &'a str,
+    }
+
+    impl Client<'_> {
+        fn request(
+            &self,
+            method: &str,
+            path: &str,
+            body: Option<String>,
+        ) -> Result<ureq::Response, Box<ureq::Error>> {
+            let request = self
+                .agent
+                .request(method, &format!("{}{}", API_URL, path))
+                .set("Authorization", &format!("Bearer {}", self.token));
+            match body {
+                Some(body) => request
+                    .set("Content-Type", "application/json")
+                    .send_string(&body),
+                None => request.call(),
+            }
+            .map_err(Box::new)
+        }
+
+        fn send(&self, method: &str, path: &str, body: Option<String>) -> Result<String, Error> {
+            self.request(method, path, body)
+                .map_err(|err| sync_error(format!("Todoist: {}", err)))?
+                .into_string()
+                .map_err(sync_error)
+        }
+
+        /// Closes or deletes a task, which is already done if it's gone.
+        fn finish(&self, method: &str, path: &str) -> Result<(), Error> {
+            match self.request(method, path, None) {
+                Ok(_) => Ok(()),
+                Err(err) if matches!(*err, ureq::Error::Status(404, _)) => Ok(()),
+                Err(err) => Err(sync_error(format!("Todoist: {}", err))),
+            }
+        }
+
+        fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+            Ok(serde_json::from_str(&self.send("GET", path, None)?)?)
+        }
+
+        fn post<T: DeserializeOwned>(&self, path: &str, body: String) -> Result<T, Error> {
+            Ok(serde_json::from_str(&self.send(
+                "POST",
+                path,
+                Some(body),
+            )?)?)
+        }
+    }
+
+    pub fn push_and_pull(config: &TodoistConfig, plan: Plan) -> Result<Outcome, Error> {
+        let client = Client {
+            agent: ureq::Agent::new(),
+            token: &config.token,
+        };
+        let mut outcome = Outcome {
+            remote_projects: client.get("/projects")?,
+            ..Outcome::default()
+        };
+        // Lists keep the project they had, or take the one named after them
+        for (list, name) in &plan.lists {
+            let known = plan
+                .projects
+                .get(list)
+                .filter(|id| outcome.remote_projects.iter().any(|p| p.id == **id));
+            let named = outcome
+                .remote_projects
+                .iter()
+                .find(|project| project.name.eq_ignore_ascii_case(name));
+            let id = match (known, named) {
+                (Some(id), _) => id.clone(),
+                (None, Some(project)) => project.id.clone(),
+                (None, None) => {
+                    let body = serde_json::json!({ "name": name }).to_string();
+                    let project: Project = client.post("/projects", body)?;
+                    let id = project.id.clone();
+                    outcome.remote_projects.push(project);
+                    id
+                }
+            };
+            outcome.projects.insert(*list, id);
+        }
+        for (list, task, mut new) in plan.create {
+            new.project_id = outcome.projects.get(&list).cloned();
+            let created: RemoteTask = client.post("/tasks", serde_json::to_string(&new)?)?;
+            outcome.created.push((list, task, created.id));
+        }
+        for (id, task) in plan.update {
+            let _: RemoteTask =
+                client.post(&format!("/tasks/{}", id), serde_json::to_string(&task)?)?;
+            outcome.updated += 1;
+        }
+        for id in plan.close {
+            client.finish("POST", &format!("/tasks/{}/close", id))?;
+            outcome.closed.push(id);
+        }
+        for id in plan.delete {
+            client.finish("DELETE", &format!("/tasks/{}", id))?;
+            outcome.deleted.push(id);
+        }
+        outcome.remote_tasks = client.get("/tasks")?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(all(test, feature = "todoist"))]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::input::tests::{app, list};
+
+    /// Lets the background Todoist sync finish and merges it in.
+    fn finish_todoist_sync(app: &mut App) {
+        for _ in 0..500 {
+            app.finish_todoist_sync().unwrap();
+            if app
+                .flash
+                .as_ref()
+                .is_some_and(|f| f.text.starts_with("Synced"))
+            {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        panic!("Todoist sync didn't finish");
+    }
+
+    #[test]
+    fn todoist_sync_pushes_new_tasks_and_pulls_projects() {
+        let mut app = app(vec![list(0, "Home", &["a", "b"])]);
+        std::fs::create_dir_all(app.db_path.parent().unwrap()).unwrap();
+        app.tasklists[0].tasks[1].status = Status::Done;
+        app.tasklists[0].tasks[1].completed_at = Some(Utc::now());
+        let projects = || {
+            vec![
+                Project {
+                    id: "1".to_string(),
+                    name: "home".to_string(),
+                },
+                Project {
+                    id: "2".to_string(),
+                    name: "Errands".to_string(),
+                },
+            ]
+        };
+        let milk = RemoteTask {
+            id: "20".to_string(),
+            project_id: "2".to_string(),
+            content: "Buy milk".to_string(),
+            labels: vec!["shop".to_string()],
+            priority: 4,
+            due: Some(Due {
+                date: "2030-01-02".to_string(),
+                datetime: None,
+            }),
+        };
+        let a = RemoteTask {
+            id: "10".to_string(),
+            project_id: "1".to_string(),
+            content: "a".to_string(),
+            labels: Vec::new(),
+            priority: 1,
+            due: None,
+        };
+        let open = vec![a, milk.clone()];
+        app.sync_todoist_with(move |plan| {
+            // Finished tasks aren't worth sending
+            assert_eq!(plan.create.len(), 1);
+            assert_eq!(plan.create[0].2.content, "a");
+            Ok(Outcome {
+                projects: vec![(0, "1".to_string())].into_iter().collect(),
+                created: vec![(0, 0, "10".to_string())],
+                remote_projects: projects(),
+                remote_tasks: open,
+                ..Outcome::default()
+            })
+        });
+        finish_todoist_sync(&mut app);
+        assert_eq!(app.tasklists[1].name, "Errands");
+        let task = &app.tasklists[1].tasks[0];
+        assert_eq!(task.name, "Buy milk");
+        assert_eq!(task.tags, ["shop"]);
+        assert_eq!(task.priority, Priority::Urgent);
+        let due = task.due_date.with_timezone(&Local).format("%Y-%m-%d");
+        assert_eq!(due.to_string(), "2030-01-02");
+
+        // Gone from the open tasks means finished in Todoist
+        app.sync_todoist_with(move |plan| {
+            assert!(plan.create.is_empty() && plan.close.is_empty());
+            Ok(Outcome {
+                remote_projects: projects(),
+                remote_tasks: vec![milk],
+                ..Outcome::default()
+            })
+        });
+        finish_todoist_sync(&mut app);
+        assert!(app.tasklists[0].tasks[0].is_completed());
+        assert_eq!(app.tasklists.len(), 2);
+    }
+}
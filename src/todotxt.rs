@@ -0,0 +1,92 @@
+use chrono::prelude::{DateTime, Utc};
+use chrono::Duration;
+
+use crate::db::{Priority, Status, Task};
+use crate::form::parse_date;
+use crate::import::Imported;
+
+/// Priorities A to C map onto Urgent, High and Medium, anything lower is Low.
+fn priority(letter: char) -> Option<Priority> {
+    match letter {
+        'A' => Some(Priority::Urgent),
+        'B' => Some(Priority::High),
+        'C' => Some(Priority::Medium),
+        'D'..='Z' => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// A todo.txt date, which is a local day.
+fn date(word: &str) -> Option<DateTime<Utc>> {
+    if word.len() != 10 {
+        return None;
+    }
+    parse_date(word, "%Y-%m-%d", Utc::now())
+}
+
+/// Reads one line of todo.txt: `x` and a completion date for done tasks, a
+/// priority like `(A)`, a creation date, then the description with
+/// `+project`s, `@context`s and `key:value` pairs mixed in. The first project
+/// picks the list, the rest become tags along with the contexts. `due:` and
+/// `t:` set the due and start dates, `pri:` the priority of a done task.
+fn parse_line(line: &str) -> Option<Imported> {
+    let mut words = line.split_whitespace().peekable();
+    let mut task = Task::new(0, String::new(), Utc::now());
+    let mut due = None;
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.status = Status::Done;
+        task.completed_at = Some(Utc::now());
+        if let Some(done) = words.peek().and_then(|word| date(word)) {
+            task.completed_at = Some(done);
+            words.next();
+        }
+    }
+    let letter = words
+        .peek()
+        .and_then(|word| word.strip_prefix('(')?.strip_suffix(')')?.parse().ok());
+    if let Some(priority) = letter.and_then(priority) {
+        task.priority = priority;
+        words.next();
+    }
+    if let Some(created) = words.peek().and_then(|word| date(word)) {
+        task.start_date = created;
+        words.next();
+    }
+    let mut project = None;
+    let mut name = Vec::new();
+    for word in words {
+        if let Some(tag) = word.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            if project.is_none() {
+                project = Some(tag.to_string());
+            } else {
+                task.tags.push(tag.to_string());
+            }
+            continue;
+        }
+        if let Some(tag) = word.strip_prefix('@').filter(|tag| !tag.is_empty()) {
+            task.tags.push(tag.to_string());
+            continue;
+        }
+        let (key, value) = word.split_once(':').unwrap_or_default();
+        let letter = value.parse().ok().and_then(priority);
+        match (key, date(value), letter) {
+            ("due", Some(day), _) => due = Some(day),
+            ("t", Some(day), _) => task.start_date = day,
+            ("pri", _, Some(priority)) => task.priority = priority,
+            _ => name.push(word),
+        }
+    }
+    if name.is_empty() {
+        return None;
+    }
+    task.name = name.join(" ");
+    // Tasks without a due date are given a day
+    task.due_date = due.unwrap_or_else(|| task.start_date.max(Utc::now()) + Duration::days(1));
+    Some(Imported { project, task })
+}
+
+/// Reads every task in a todo.txt file, skipping blank lines.
+pub fn parse_todotxt(content: &str) -> Vec<Imported> {
+    content.lines().filter_map(parse_line).collect()
+}
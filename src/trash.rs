@@ -0,0 +1,76 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::db::{write_atomic, Error, Task};
+use crate::state::beside_db;
+
+const TRASH_FILE: &str = "trash.json";
+
+/// How many deleted tasks are kept around to restore
+pub const TRASH_LIMIT: usize = 100;
+
+/// A deleted task and where it was, so it can be put back.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trashed {
+    pub list_id: usize,
+    pub position: usize,
+    pub task: Task,
+}
+
+pub fn read_trash(db_path: &Path) -> Vec<Trashed> {
+    let path = beside_db(db_path, TRASH_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|err| {
+        warn!(
+            "couldn't parse {}, starting with an empty trash: {}",
+            path.display(),
+            err
+        );
+        Vec::new()
+    })
+}
+
+pub fn write_trash(db_path: &Path, trash: &[Trashed]) -> Result<(), Error> {
+    write_atomic(
+        &beside_db(db_path, TRASH_FILE),
+        &serde_json::to_string_pretty(trash)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::config::Config;
+    use crate::db::tests::scratch_db;
+    use crate::state::State;
+    use chrono::Utc;
+
+    #[test]
+    fn dbs_in_one_directory_keep_their_own_trash() {
+        let db = scratch_db();
+        let demo = db.with_file_name("demo.json");
+        let trashed = Trashed {
+            list_id: 0,
+            position: 0,
+            task: Task::new(0, "a".to_string(), Utc::now()),
+        };
+        write_trash(&db, &[trashed]).unwrap();
+        assert!(read_trash(&demo).is_empty());
+
+        // Quitting the other db empties only its own trash
+        let config = Config {
+            keep_trash: false,
+            ..Config::default()
+        };
+        let mut app = App::with_tasks(config, State::default(), demo, Vec::new());
+        app.quit(true).unwrap();
+        assert_eq!(read_trash(&db)[0].task.name, "a");
+    }
+}